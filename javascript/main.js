@@ -6,6 +6,15 @@ const kaoScreeps = {
     Game.notify(args.join(' '));
   },
 
+  // Console helpers, tasks can be given as JSON or as plain objects
+  exposeCommands : () => {
+    global.addTask = (creepName, task, priority) => kaoScreeps.wasm.add_task(creepName, typeof task === 'string' ? task : JSON.stringify(task), priority);
+    global.listTasks = (creepName) => kaoScreeps.wasm.list_tasks(creepName);
+    global.cancelTasks = (creepName) => kaoScreeps.wasm.cancel_tasks(creepName);
+    global.listSpawnQueue = () => kaoScreeps.wasm.list_spawn_queue();
+    global.setMiningMode = (roomName, sourceId, mode) => kaoScreeps.wasm.set_mining_mode(roomName, sourceId, JSON.stringify(mode));
+  },
+
   loop : () => {
     console.log(`GAME TICK ${Game.time}`)
     try {
@@ -15,6 +24,7 @@ const kaoScreeps = {
         kaoScreeps.wasm = require('kaoscreeps-rust');
         kaoScreeps.wasm.initialize_instance();
         kaoScreeps.wasm.setup();
+        kaoScreeps.exposeCommands();
       }
       kaoScreeps.wasm.game_loop();
     } catch(err) {
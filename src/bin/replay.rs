@@ -0,0 +1,44 @@
+#[cfg(not(target_arch = "wasm32"))]
+use kaoscreeps_rust::recorder::{replay, IntentDiff, TickRecord};
+
+// Each argument is the contents of one recorder segment saved to a file
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+  let paths = std::env::args().skip(1).collect::<Vec<_>>();
+  if paths.is_empty() {
+    eprintln!("Usage: replay <segment dump>...");
+    std::process::exit(2);
+  }
+
+  let mut records = paths.iter()
+    .map(|path| {
+      let contents = std::fs::read_to_string(path).unwrap_or_else(|e| panic!("Unable to read {}: {}", path, e));
+      serde_json::from_str::<TickRecord>(&contents).unwrap_or_else(|e| panic!("Unable to parse {}: {}", path, e))
+    })
+    .collect::<Vec<_>>();
+  records.sort_by_key(|record| record.world.time);
+
+  let mut diverged = false;
+  for record in records.iter() {
+    let diff = IntentDiff::between(&record.intents, &replay(record));
+    if diff.is_empty() {
+      println!("tick {}: {} intents match", record.world.time, record.intents.len());
+    } else {
+      diverged = true;
+      println!("tick {}: decisions diverged", record.world.time);
+      for intent in diff.missing.iter() {
+        println!("  - {:?}", intent);
+      }
+      for intent in diff.extra.iter() {
+        println!("  + {:?}", intent);
+      }
+    }
+  }
+
+  if diverged {
+    std::process::exit(1);
+  }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn main() {}
@@ -0,0 +1,55 @@
+#[cfg(not(target_arch = "wasm32"))]
+use kaoscreeps_rust::{sim::Simulation, world::{MemoryWorld, new_position}};
+#[cfg(not(target_arch = "wasm32"))]
+use screeps::{RoomName, StructureType, Terrain};
+
+#[cfg(not(target_arch = "wasm32"))]
+fn default_world() -> MemoryWorld {
+  let room_name = RoomName::new("W1N1").unwrap();
+  let mut world = MemoryWorld::new();
+  world.add_room(room_name);
+
+  // A swamp between the spawn and the far source and a wall the haulers have to path around
+  for x in 28..36 {
+    for y in 30..36 {
+      world.set_terrain(new_position(x, y, room_name), Terrain::Swamp);
+    }
+  }
+  for y in 15..24 {
+    world.set_terrain(new_position(18, y, room_name), Terrain::Wall);
+  }
+
+  world.add_structure(StructureType::Spawn, new_position(25, 25, room_name), 300, 300);
+  world.add_structure(StructureType::Extension, new_position(24, 27, room_name), 0, 50);
+  world.add_structure(StructureType::Extension, new_position(26, 27, room_name), 0, 50);
+
+  world.add_source(new_position(12, 18, room_name), screeps::constants::SOURCE_ENERGY_CAPACITY);
+  world.add_source(new_position(40, 38, room_name), screeps::constants::SOURCE_ENERGY_CAPACITY);
+
+  world
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+  let mut args = std::env::args().skip(1);
+  let ticks = args.next().and_then(|arg| arg.parse().ok()).unwrap_or(3000);
+  let report_interval = args.next().and_then(|arg| arg.parse().ok()).unwrap_or(500);
+
+  let mut simulation = Simulation::new(default_world());
+  simulation.run(ticks, |world| {
+    if world.time % report_interval == 0 {
+      println!("tick {:>6} | creeps {:>2} | {:?}", world.time, world.creeps.len(), world.stats);
+    }
+  });
+
+  let stats = &simulation.world.stats;
+  println!("Simulated {} ticks", ticks);
+  println!("  energy harvested : {}", stats.energy_harvested);
+  println!("  energy delivered : {} ({:.2}/tick)", stats.energy_delivered, stats.energy_delivered as f64 / ticks as f64);
+  println!("  energy decayed   : {}", stats.energy_decayed);
+  println!("  creeps spawned   : {}", stats.creeps_spawned);
+  println!("  creeps died      : {}", stats.creeps_died);
+}
+
+#[cfg(target_arch = "wasm32")]
+fn main() {}
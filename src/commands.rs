@@ -0,0 +1,97 @@
+use screeps::{RawObjectId, RoomName};
+use serde_json::Value;
+
+use crate::hive::Hive;
+use crate::hive::mine_room::MiningMode;
+use crate::hive::tasks::{ScheduledTask, Task, TaskPriority, Tasks};
+use crate::world::GameWorld;
+
+// Commands run from the in-game console. Each returns the text to print there.
+
+pub fn add_task(tasks: &mut Tasks, world: &dyn GameWorld, creep_name: &str, task_json: &str, priority: Option<&str>) -> Result<String,String> {
+  if world.creep(creep_name).is_none() {
+    return Err(format!("Creep {:?} does not exist", creep_name));
+  }
+  let task: Task = serde_json::from_str(task_json).map_err(|e| format!("Unable to parse task: {}", e))?;
+  task.validate(world)?;
+
+  let summary = format!("{} for {:?}", task.label(), creep_name);
+  match priority {
+    Some(priority) => {
+      let priority: TaskPriority = serde_json::from_value(Value::String(priority.to_owned()))
+        .map_err(|_| format!("Unknown priority {:?}, expected Low, Normal, High or Urgent", priority))?;
+      tasks.add_scheduled_task(creep_name.to_owned(), ScheduledTask::new(task).with_priority(priority))?;
+    },
+    None => tasks.add_task(creep_name.to_owned(), task)?,
+  }
+  Ok(format!("Added {}", summary))
+}
+
+// Every task, or only the given creep's, with the one that runs next listed first
+pub fn list_tasks(tasks: &Tasks, creep_name: Option<&str>) -> String {
+  let mut lines = vec![];
+
+  let mut creep_names = tasks.task_list.keys().filter(|name| creep_name.map_or(true, |creep_name| creep_name == name.as_str())).collect::<Vec<_>>();
+  creep_names.sort();
+  for name in creep_names {
+    for scheduled_task in tasks.task_list[name].iter().rev() {
+      let task_json = serde_json::to_string(&scheduled_task.task).unwrap_or_else(|e| format!("<{}>", e));
+      lines.push(format!("{} [{:?}] {}", name, scheduled_task.priority, task_json));
+    }
+  }
+
+  let mut leader_names = tasks.groups.iter()
+    .filter(|(leader_name, group_task)| creep_name.map_or(true, |creep_name| group_task.members(leader_name).any(|member_name| member_name == creep_name)))
+    .map(|(leader_name, _)| leader_name)
+    .collect::<Vec<_>>();
+  leader_names.sort();
+  for leader_name in leader_names {
+    let group_task = &tasks.groups[leader_name];
+    let action_json = serde_json::to_string(&group_task.action).unwrap_or_else(|e| format!("<{}>", e));
+    lines.push(format!("{} leading {:?} {}", leader_name, group_task.followers, action_json));
+  }
+
+  if lines.is_empty() {
+    String::from("No tasks")
+  } else {
+    lines.join("\n")
+  }
+}
+
+pub fn cancel_tasks(tasks: &mut Tasks, creep_name: &str) -> Result<String,String> {
+  match tasks.cancel(creep_name) {
+    0 => Err(format!("Creep {:?} has no tasks", creep_name)),
+    cancelled => Ok(format!("Cancelled {} task(s) for {:?}", cancelled, creep_name)),
+  }
+}
+
+pub fn list_spawn_queue(hive: &Hive) -> String {
+  let mut spawn_rooms = hive.spawn_rooms.values().collect::<Vec<_>>();
+  spawn_rooms.sort_by_key(|spawn_room| spawn_room.room_name.to_string());
+
+  let lines = spawn_rooms.into_iter()
+    .flat_map(|spawn_room| spawn_room.pending_requests().iter().enumerate().map(move |(index, request)| {
+      let dispatched = request.dispatched.map(|tick| format!(", ordered on tick {}", tick)).unwrap_or_default();
+      format!("{} #{} {} [{:?}] costs {}, requested on tick {}{}", spawn_room.room_name, index, request.name, request.priority, request.cost(), request.requested, dispatched)
+    }))
+    .collect::<Vec<_>>();
+
+  if lines.is_empty() {
+    String::from("Nothing queued")
+  } else {
+    lines.join("\n")
+  }
+}
+
+pub fn set_mining_mode(hive: &mut Hive, room_name: &str, source_id: &str, mining_mode_json: &str) -> Result<String,String> {
+  let room_name = RoomName::new(room_name).map_err(|_| format!("Invalid room name {:?}", room_name))?;
+  let source_id: RawObjectId = source_id.parse().map_err(|_| format!("Invalid source id {:?}", source_id))?;
+  let mining_mode: MiningMode = serde_json::from_str(mining_mode_json).map_err(|e| format!("Unable to parse mining mode: {}", e))?;
+
+  let mine_room = hive.mine_rooms.get_mut(&room_name).ok_or_else(|| format!("{} is not mined", room_name))?;
+  if !mine_room.source_ids.contains(&source_id) {
+    return Err(format!("Source {} is not mined from {}", source_id, room_name));
+  }
+  mine_room.set_mining_mode(source_id, mining_mode);
+  Ok(format!("Source {} in {} is now mined with {:?}", source_id, room_name, mining_mode))
+}
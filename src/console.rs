@@ -1,4 +1,6 @@
+#[cfg(target_arch = "wasm32")]
 use js_sys::JsString;
+#[cfg(target_arch = "wasm32")]
 use web_sys::console;
 
 pub fn colorize<T, C>(text: T, color: C) -> String
@@ -13,6 +15,7 @@ where
     )
 }
 
+#[cfg(target_arch = "wasm32")]
 pub fn log<S: AsRef<str>>(str: S) {
     #[allow(unused_unsafe)]
     unsafe {
@@ -20,6 +23,12 @@ pub fn log<S: AsRef<str>>(str: S) {
     }
 }
 
+// Native builds (tests, tooling) have no JS console to write to
+#[cfg(not(target_arch = "wasm32"))]
+pub fn log<S: AsRef<str>>(str: S) {
+    eprintln!("{}", str.as_ref());
+}
+
 pub fn info<S: AsRef<str>>(str: S) {
     log(format!("[INFO] {}", str.as_ref()))
 }
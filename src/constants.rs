@@ -3,5 +3,27 @@ pub const LOOKUP_OFFSET: u32 = 0;
 
 pub const SPAWN_INTERVAL: u32 = 5;
 pub const SPAWN_OFFSET: u32 = 1;
+pub const SPAWN_REQUEST_TIMEOUT: u32 = 20; // Ticks a spawn request is kept without being asked for again
 
-pub const ROOM_SIZE: (u32,u32) = (50,50);
\ No newline at end of file
+pub const CREEP_PART_LIMIT: u32 = 50;
+
+pub const ROOM_SIZE: (u32,u32) = (50,50);
+
+pub const MOVE_STUCK_TICKS: u32 = 3; // Ticks without moving before a path is searched again
+pub const TASK_RETRIES: u32 = 3; // Recoverable failures in a row before a task is abandoned
+
+pub const MEMORY_SAVE_INTERVAL: u32 = 10;
+
+pub const SEGMENT_SIZE_LIMIT: usize = 100 * 1024;
+pub const MAX_ACTIVE_SEGMENTS: usize = 10;
+
+pub const STORAGE_SEGMENT_START: u8 = 0;
+pub const STORAGE_SEGMENT_COUNT: u8 = 60;
+
+pub const BACKUP_SEGMENT_START: u8 = 60;
+pub const BACKUP_SLOTS: u8 = 2;
+pub const BACKUP_SLOT_SEGMENTS: u8 = 5;
+pub const BACKUP_INTERVAL: u32 = 1000;
+
+pub const RECORDER_SEGMENT_START: u8 = 80;
+pub const RECORDER_SEGMENT_COUNT: u8 = 20;
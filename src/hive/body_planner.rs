@@ -0,0 +1,135 @@
+use screeps::Part;
+
+use crate::constants::CREEP_PART_LIMIT;
+
+// Ground a creep has to cross at full speed, fully loaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ground {
+  Road,
+  Plain,
+  Swamp,
+}
+
+impl Ground {
+  // Fatigue each part other than MOVE generates per tile
+  fn fatigue(self) -> u32 {
+    match self {
+      Ground::Road => 1,
+      Ground::Plain => 2,
+      Ground::Swamp => 10,
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BodyTemplate {
+  pub prefix: Vec<Part>, // Always included once
+  pub segment: Vec<Part>, // Repeated as often as the budget and max_parts allow
+  pub max_parts: u32, // Capped at the creep part limit
+  pub movement: Option<Ground>, // None for creeps that are towed or never move, MOVE parts are added to match otherwise
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BodyError {
+  Empty, // The template has no parts
+  TooExpensive { cost: u32, budget: u32 }, // Cost of the smallest body
+  TooLarge { parts: u32, max_parts: u32 }, // Size of the smallest body
+}
+
+impl From<BodyError> for String {
+  fn from(body_error: BodyError) -> Self {
+    format!("{:?}", body_error)
+  }
+}
+
+impl BodyTemplate {
+  pub fn new(segment: Vec<Part>) -> Self {
+    Self {
+      prefix: vec![],
+      segment,
+      max_parts: CREEP_PART_LIMIT,
+      movement: Some(Ground::Plain),
+    }
+  }
+
+  pub fn with_prefix(mut self, prefix: Vec<Part>) -> Self {
+    self.prefix = prefix;
+    self
+  }
+
+  pub fn with_max_parts(mut self, max_parts: u32) -> Self {
+    self.max_parts = max_parts;
+    self
+  }
+
+  pub fn with_movement(mut self, movement: Option<Ground>) -> Self {
+    self.movement = movement;
+    self
+  }
+
+  // The prefix, `segments` copies of the segment and the MOVE parts they need
+  fn body(&self, segments: u32) -> Vec<Part> {
+    let mut body = self.prefix.to_owned();
+    for _ in 0..segments {
+      body.extend(self.segment.iter().copied());
+    }
+    if let Some(ground) = self.movement {
+      let moves = body.iter().filter(|part| **part == Part::Move).count() as u32;
+      let others = body.len() as u32 - moves;
+      let required = (others * ground.fatigue()).div_ceil(2);
+      body.extend(std::iter::repeat(Part::Move).take(required.saturating_sub(moves) as usize));
+    }
+    body
+  }
+
+  // The largest body within the budget, ordered so the parts that matter most are the last to be destroyed
+  pub fn plan(&self, budget: u32) -> Result<Vec<Part>,BodyError> {
+    let max_parts = self.max_parts.min(CREEP_PART_LIMIT);
+    let fits = |body: &[Part]| body_cost(body) <= budget && body.len() as u32 <= max_parts;
+
+    if self.prefix.is_empty() && self.segment.is_empty() {
+      return Err(BodyError::Empty);
+    }
+    let min_segments = if self.prefix.is_empty() { 1 } else { 0 };
+    let mut body = self.body(min_segments);
+    if body.len() as u32 > max_parts {
+      return Err(BodyError::TooLarge { parts: body.len() as u32, max_parts });
+    }
+    if body_cost(&body) > budget {
+      return Err(BodyError::TooExpensive { cost: body_cost(&body), budget });
+    }
+
+    if !self.segment.is_empty() {
+      for segments in (min_segments + 1).. {
+        let larger = self.body(segments);
+        if !fits(&larger) {
+          break;
+        }
+        body = larger;
+      }
+    }
+
+    body.sort_by_key(|part| durability_rank(*part));
+    Ok(body)
+  }
+}
+
+pub fn body_cost(body: &[Part]) -> u32 {
+  body.iter().map(|part| part.cost()).sum()
+}
+
+// Damage takes out parts from the front. TOUGH soaks it up, MOVE goes late so a damaged creep can still retreat, and
+// HEAL goes last so it can repair the rest.
+fn durability_rank(part: Part) -> u8 {
+  match part {
+    Part::Tough => 0,
+    Part::Work => 1,
+    Part::Carry => 2,
+    Part::Claim => 3,
+    Part::Attack => 4,
+    Part::RangedAttack => 5,
+    Part::Move => 6,
+    Part::Heal => 7,
+    _ => 1,
+  }
+}
@@ -1,6 +1,8 @@
-use screeps::{RawObjectId, RoomPosition, ObjectId, StructureSpawn, game, Creep, ReturnCode, SharedCreepProperties, ResourceType, HasId};
+use screeps::{RawObjectId, Position, ReturnCode, ResourceType};
 use serde::{Serialize, Deserialize};
 
+use crate::world::{GameWorld, CreepState, StructureState};
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum EnergyDropOff {
   Spawn(RawObjectId), // RawObjectId == ObjectId<StructureSpawn>
@@ -8,43 +10,43 @@ pub enum EnergyDropOff {
 }
 
 impl EnergyDropOff {
-  pub fn preload(&self) -> Option<EnergyDropOffLoaded> {
+  pub fn preload(&self, world: &dyn GameWorld) -> Option<EnergyDropOffLoaded> {
     match self {
-      EnergyDropOff::Spawn(spawn_id) => ObjectId::<StructureSpawn>::from(spawn_id.to_owned()).resolve().map(EnergyDropOffLoaded::Spawn),
-      EnergyDropOff::Creep(creep_name) => game::creeps().get(creep_name.to_owned()).map(EnergyDropOffLoaded::Creep),
+      EnergyDropOff::Spawn(spawn_id) => world.structure(spawn_id.to_owned()).map(EnergyDropOffLoaded::Spawn),
+      EnergyDropOff::Creep(creep_name) => world.creep(creep_name).map(EnergyDropOffLoaded::Creep),
     }
   }
 }
 
 pub enum EnergyDropOffLoaded {
-  Spawn(StructureSpawn),
-  Creep(Creep),
+  Spawn(StructureState),
+  Creep(CreepState),
 }
 
 impl EnergyDropOffLoaded {
   pub fn compress(&self) -> EnergyDropOff {
     match self {
-      EnergyDropOffLoaded::Spawn(spawn) => EnergyDropOff::Spawn(spawn.raw_id()),
-      EnergyDropOffLoaded::Creep(creep) => EnergyDropOff::Creep(creep.name()),
+      EnergyDropOffLoaded::Spawn(spawn) => EnergyDropOff::Spawn(spawn.id),
+      EnergyDropOffLoaded::Creep(creep) => EnergyDropOff::Creep(creep.name.to_owned()),
     }
   }
 
-  pub fn pos(&self) -> RoomPosition {
+  pub fn pos(&self) -> Position {
     match self {
-      Self::Spawn(spawn) => spawn.pos(),
-      Self::Creep(creep) => creep.pos(),
+      Self::Spawn(spawn) => spawn.pos,
+      Self::Creep(creep) => creep.pos,
     }
   }
 
-  pub fn accept_energy(&self, hauler: &Creep, amount: Option<u32>) -> Result<ReturnCode,ReturnCode> {
-    let return_code = match self {
-      Self::Spawn(spawn) => hauler.transfer(spawn, ResourceType::Energy, amount),
-      Self::Creep(creep) => hauler.transfer(creep, ResourceType::Energy, amount),
+  pub fn accept_energy(&self, world: &mut dyn GameWorld, hauler_name: &str, amount: Option<u32>) -> Result<ReturnCode,ReturnCode> {
+    let target_id = match self {
+      Self::Spawn(spawn) => spawn.id,
+      Self::Creep(creep) => creep.id.ok_or(ReturnCode::InvalidTarget)?,
     };
 
-    match return_code {
+    match world.creep_transfer(hauler_name, target_id, ResourceType::Energy, amount) {
       ReturnCode::Ok => Ok(ReturnCode::Ok),
       failure_code => Err(failure_code),
     }
   }
-}
\ No newline at end of file
+}
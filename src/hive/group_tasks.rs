@@ -0,0 +1,148 @@
+use screeps::{Position, ReturnCode};
+use serde::{Serialize, Deserialize};
+
+use crate::constants::TASK_RETRIES;
+use crate::world::{CreepState, GameWorld};
+use super::tasks::{Deadline, Failure, TaskError, TaskOwner, TaskReturn, TaskStatus, TaskTarget, TravelPath};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum GroupAction {
+  // The leader pulls its followers along in a line, then swaps places with the first follower to leave it in range
+  Tow { target: Position, range: u32, #[serde(default)] path: TravelPath },
+}
+
+impl GroupAction {
+  fn label(&self) -> &'static str {
+    match self {
+      GroupAction::Tow { .. } => "Tow",
+    }
+  }
+
+  fn target(&self) -> Option<TaskTarget> {
+    match self {
+      GroupAction::Tow { target, .. } => Some(TaskTarget::Position(*target)),
+    }
+  }
+
+  fn run(&mut self, world: &mut dyn GameWorld, leader: &CreepState, followers: &[CreepState]) -> TaskReturn {
+    match self.execute(world, leader, followers) {
+      TaskReturn::Err(error) => TaskReturn::Err(error.within(self.label(), self.target(), world.time())),
+      task_return => task_return,
+    }
+  }
+
+  fn execute(&mut self, world: &mut dyn GameWorld, leader: &CreepState, followers: &[CreepState]) -> TaskReturn {
+    match self {
+
+      GroupAction::Tow { target, range, path } => {
+        let delivered = match followers.first() {
+          Some(delivered) => delivered,
+          None => return TaskReturn::Err(TaskError::new(ReturnCode::InvalidArgs)),
+        };
+        if delivered.pos.room_name() == target.room_name() && delivered.pos.get_range_to(target) <= *range {
+          return TaskReturn::Complete;
+        }
+        if !leader.pos.is_near_to(&delivered.pos) {
+          return match world.creep_move_to(&leader.name, delivered.pos) {
+            ReturnCode::Ok | ReturnCode::Tired => TaskReturn::ProgressMade,
+            return_code => TaskReturn::Err(TaskError::new(return_code)),
+          };
+        }
+
+        // Every member pulls the one behind it
+        let mut puller = leader;
+        for follower in followers {
+          if !puller.pos.is_near_to(&follower.pos) {
+            return TaskReturn::Err(TaskError::new(ReturnCode::NotInRange).within("Follow", Some(TaskTarget::Creep(follower.name.to_owned())), world.time()));
+          }
+          match (world.creep_pull(&puller.name, &follower.name), world.creep_move_pulled_by(&follower.name, &puller.name)) {
+            (ReturnCode::Ok, ReturnCode::Ok) => {},
+            (pull_code, follow_code) => {
+              return TaskReturn::Err(TaskError::from_codes(vec![pull_code, follow_code]).within("Follow", Some(TaskTarget::Creep(follower.name.to_owned())), world.time()));
+            },
+          }
+          puller = follower;
+        }
+
+        if leader.pos.room_name() == target.room_name() && leader.pos.get_range_to(target) <= *range {
+          match world.creep_move_pulled_by(&leader.name, &delivered.name) {
+            ReturnCode::Ok => TaskReturn::ProgressMade,
+            return_code => TaskReturn::Err(TaskError::new(return_code)),
+          }
+        } else {
+          path.travel(world, leader, *target, *range)
+        }
+      },
+
+    }
+  }
+}
+
+// One action shared by several creeps. Every member counts as busy until the group is released, which happens to all
+// of them at once when the action ends or any member dies.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GroupTask {
+  pub followers: Vec<String>, // String == Valid creep name, in order behind the leader
+  pub action: GroupAction,
+  pub owner: Option<TaskOwner>,
+  pub deadline: Option<Deadline>,
+  pub started: Option<u32>,
+  pub max_retries: u32,
+  pub retries: u32,
+}
+
+impl GroupTask {
+  pub fn new(followers: Vec<String>, action: GroupAction) -> Self {
+    Self {
+      followers,
+      action,
+      owner: None,
+      deadline: None,
+      started: None,
+      max_retries: TASK_RETRIES,
+      retries: 0,
+    }
+  }
+
+  pub fn with_owner(mut self, owner: TaskOwner) -> Self {
+    self.owner = Some(owner);
+    self
+  }
+
+  pub fn with_deadline(mut self, deadline: Deadline) -> Self {
+    self.deadline = Some(deadline);
+    self
+  }
+
+  pub fn run(&mut self, world: &mut dyn GameWorld, leader_name: &str) -> TaskStatus {
+    let time = world.time();
+    let started = *self.started.get_or_insert(time);
+    if self.deadline.map_or(false, |deadline| deadline.passed(time, started)) {
+      return TaskStatus::TimedOut;
+    }
+
+    let leader = world.creep(leader_name);
+    let followers = self.followers.iter().map(|follower_name| world.creep(follower_name)).collect::<Option<Vec<_>>>();
+    let (leader, followers) = match (leader, followers) {
+      (Some(leader), Some(followers)) => (leader, followers),
+      _ => return TaskStatus::CreepDied,
+    };
+
+    match self.action.run(world, &leader, &followers) {
+      TaskReturn::ProgressMade => {
+        self.retries = 0;
+        TaskStatus::Running
+      },
+      TaskReturn::Complete => TaskStatus::Completed,
+      TaskReturn::Err(error) if error.failure() == Failure::Recoverable && self.retries < self.max_retries => {
+        self.retries += 1;
+        TaskStatus::Running
+      },
+      TaskReturn::Err(error) => TaskStatus::Failed(error),
+    }
+  }
+
+  pub fn members<'a>(&'a self, leader_name: &'a str) -> impl Iterator<Item = &'a str> {
+    std::iter::once(leader_name).chain(self.followers.iter().map(String::as_str))
+  }
+}
@@ -1,8 +1,12 @@
-use screeps::{SharedCreepProperties, HasId, RawObjectId, Room, find, Source, Part, Creep, ObjectId, RoomName, ReturnCode, ResourceType, look};
+use std::collections::HashMap;
+
+use screeps::{Direction, Part, Position, RawObjectId, ResourceType, ReturnCode, RoomName, StructureType};
 use serde::{Serialize, Deserialize};
 
 use crate::console;
-use super::{Hive, energy_distributer::{EnergyDropOff, EnergyDropOffLoaded}};
+use crate::constants::CREEP_PART_LIMIT;
+use crate::world::{GameWorld, ConstructionSiteState, CreepState, SourceState, StructureState, step};
+use super::{GetCreepError, Hive, role_names, body_planner::{BodyError, BodyTemplate, Ground, body_cost}, spawn_room::SpawnPriority, energy_distributer::{EnergyDropOff, EnergyDropOffLoaded}, group_tasks::{GroupAction, GroupTask}, tasks::{TaskEvent, TaskOwner, TaskStatus}};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct MineRoom {
@@ -10,154 +14,459 @@ pub struct MineRoom {
   pub spawn_room_name: RoomName,
   pub energy_drop_off: EnergyDropOff,
   pub source_ids: Vec<RawObjectId>, // RawObjectId == ObjectId<Source>
+  pub round_trips: HashMap<String,u32>, // String == Source id, ticks for a hauler to reach the drop off and come back
+  pub mining_modes: HashMap<String,MiningMode>, // String == Source id, sources that are not listed are drop mined
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum MiningMode {
+  Drop, // The harvester holds what it can and drops the rest for haulers to pick up
+  Container, // The harvester stands on a container built on its access tile and haulers empty the container
+  Link { link: RawObjectId, receiver: RawObjectId }, // The harvester fills a link next to the source, which sends its energy to `receiver` for haulers to empty
+}
+
+impl Default for MiningMode {
+  fn default() -> Self {
+    MiningMode::Drop
+  }
+}
+
+// Where haulers collect a source's energy from
+enum Pickup {
+  Harvester(CreepState), // Takes what the harvester holds as well as what it dropped
+  Tile(Position), // Only what was dropped there
+  Store(StructureState),
+}
+
+// How many haulers a source needs and how many CARRY parts each of them gets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HaulerPlan {
+  pub count: u32,
+  pub carry_parts: u32,
 }
 
 impl MineRoom {
-  pub fn new(room: &Room, spawn_room_name: RoomName, energy_drop_off: EnergyDropOffLoaded) -> Self {
+  pub fn new(world: &dyn GameWorld, room_name: RoomName, spawn_room_name: RoomName, energy_drop_off: EnergyDropOffLoaded) -> Self {
     let drop_off_pos = energy_drop_off.pos();
 
-    let mut sources = room.find(find::SOURCES)
+    let mut sources = world.find_sources(room_name)
       .into_iter()
-      .map(|source| (source.pos().get_range_to(&drop_off_pos),source))
+      .map(|source| (source.pos.get_range_to(&drop_off_pos),source))
       .collect::<Vec<_>>();
 
     sources.sort_by(|a,b| a.0.cmp(&b.0));
 
     Self {
-      room_name: room.name(),
+      room_name,
       spawn_room_name,
       energy_drop_off : energy_drop_off.compress(),
       source_ids: sources
         .into_iter()
-        .map(|entry| entry.1.raw_id())
+        .map(|entry| entry.1.id)
         .collect(),
+      round_trips: HashMap::new(),
+      mining_modes: HashMap::new(),
     }
   }
 
-  // TODO : calculate required CARRY parts based on how much energy will be harvested between hauler visits and store energy in harvester if less is wasted
-  pub fn get_harvester_body(energy_available: u32) -> Vec<Part> {
-    let req_work = screeps::constants::SOURCE_ENERGY_CAPACITY / screeps::constants::ENERGY_REGEN_TIME / 2;
-    let max_addl_work = (energy_available - Part::Work.cost()) / Part::Work.cost();
+  pub fn mining_mode(&self, source_id: RawObjectId) -> MiningMode {
+    self.mining_modes.get(&String::from(source_id)).copied().unwrap_or_default()
+  }
 
-    let mut harvester_body = vec![Part::Work];
-    for _ in 0..max_addl_work.min(req_work - 1) {
-      harvester_body.insert(harvester_body.len(), Part::Work);
-    }
-    harvester_body
+  // Haulers may start from somewhere else in the new mode, so the round trip is measured again
+  pub fn set_mining_mode(&mut self, source_id: RawObjectId, mining_mode: MiningMode) {
+    let source_key = String::from(source_id);
+    self.round_trips.remove(&source_key);
+    match mining_mode {
+      MiningMode::Drop => self.mining_modes.remove(&source_key),
+      mining_mode => self.mining_modes.insert(source_key, mining_mode),
+    };
   }
 
-  // TODO : calculate required size based on how much energy needs to be hauled and how far it will be hauled
-  pub fn get_hauler_body(_energy_available: u32) -> Vec<Part> {
-    vec![Part::Move,Part::Carry,Part::Move,Part::Carry]
+  // WORK comes first, CARRY only gets what is left of the budget once the source can be emptied
+  pub fn get_harvester_body(carry_parts: u32, energy_available: u32) -> Result<Vec<Part>,BodyError> {
+    let req_work = screeps::constants::SOURCE_ENERGY_CAPACITY / screeps::constants::ENERGY_REGEN_TIME / 2;
+    // Harvesters are towed into place and never move again
+    let work_parts = BodyTemplate::new(vec![Part::Work])
+      .with_max_parts(req_work)
+      .with_movement(None)
+      .plan(energy_available)?
+      .len() as u32;
+    let carry_parts = carry_parts
+      .min((energy_available - work_parts * Part::Work.cost()) / Part::Carry.cost())
+      .min(CREEP_PART_LIMIT - work_parts);
+    BodyTemplate::new(vec![])
+      .with_prefix([vec![Part::Work; work_parts as usize], vec![Part::Carry; carry_parts as usize]].concat())
+      .with_movement(None)
+      .plan(energy_available)
   }
 
-  fn tow_to_source(&self, source: Source, harvester: &Creep, hauler: &Creep) -> Result<(),String> {
-    if hauler.pos().is_near_to(harvester) {
-      match (hauler.pull(harvester),harvester.move_pulled_by(hauler)) {
-        (screeps::ReturnCode::Ok,screeps::ReturnCode::Ok) => {
-          if hauler.pos().is_near_to(&source) {
-            match hauler.move_pulled_by(harvester) {
-              screeps::ReturnCode::Ok => Ok(()),
-              unexpected => {
-                Err(format!("Creep {:?} unexpected return code when dumping harvester: {:?}", hauler.name(), unexpected))
-              },
-            }
-          } else {
-            match hauler.move_to(&source) {
-              screeps::ReturnCode::Ok => Ok(()),
-              unexpected => {
-                Err(format!("Creep {:?} unexpected return code when moving to source: {:?}", hauler.name(), unexpected))
-              },
-            }
-          }
-        },
-        unexpected => {
-          Err(format!("Creep {:?} unexpected return code when pulling creep {:?}. Pull code: {:?}, Follow code: {:?}", hauler.name(), harvester.name(), unexpected.0, unexpected.1))
-        },
+  // A dropped pile loses about a thousandth of itself every tick until it is picked up, while energy held by the
+  // harvester is only lost if it dies. Held energy grows with the harvest until the CARRY parts are full and stays there
+  // until the next visit, so each part keeps less out of the pile than it holds. Takes the number of CARRY parts that
+  // saves the most over the harvester's life once what they add to its body is paid for, which can be none.
+  pub fn drop_harvester_carry_parts(energy_per_tick: u32, visit_interval: u32, energy_available: u32) -> u32 {
+    let harvested = energy_per_tick * visit_interval;
+    let base_cost = match Self::get_harvester_body(0, energy_available) {
+      Ok(body) if harvested > 0 => body_cost(&body),
+      _ => return 0,
+    };
+
+    let mut best = (0, 0.0);
+    for carry_parts in 1..=harvested.div_ceil(screeps::constants::CARRY_CAPACITY).min(CREEP_PART_LIMIT) {
+      let body = match Self::get_harvester_body(carry_parts, energy_available) {
+        Ok(body) => body,
+        Err(_) => break,
+      };
+      // The budget or part limit may have left out some of the CARRY asked for
+      let fitted = body.iter().filter(|part| **part == Part::Carry).count() as u32;
+      let capacity = (fitted * screeps::constants::CARRY_CAPACITY).min(harvested) as f64;
+      let average_held = capacity - capacity * capacity / (2 * harvested) as f64;
+      let saved = average_held * screeps::constants::CREEP_LIFE_TIME as f64 / screeps::constants::ENERGY_DECAY as f64;
+      let net = saved - (body_cost(&body) - base_cost) as f64;
+      if net > best.1 {
+        best = (fitted, net);
       }
-    } else {
-      match hauler.move_to(&harvester) {
-        ReturnCode::Ok => Ok(()),
-        failure_reason => Err(format!("{:?}",failure_reason)),
+      if fitted < carry_parts {
+        break;
       }
     }
+    best.0
+  }
+
+  fn harvester_carry_parts(mining_mode: MiningMode, energy_per_tick: u32, round_trip: u32, hauler_plan: HaulerPlan, energy_available: u32) -> u32 {
+    match mining_mode {
+      MiningMode::Drop => Self::drop_harvester_carry_parts(energy_per_tick, round_trip.div_ceil(hauler_plan.count), energy_available),
+      // Enough to build the container or to fill the link while harvesting
+      MiningMode::Container | MiningMode::Link { .. } => 1,
+    }
+  }
+
+  pub fn get_hauler_body(carry_parts: u32, energy_available: u32) -> Result<Vec<Part>,BodyError> {
+    BodyTemplate::new(vec![Part::Carry])
+      .with_max_parts(carry_parts * 2)
+      .with_movement(Some(Ground::Plain))
+      .plan(energy_available)
+  }
+
+  // Enough CARRY to move everything the source regenerates while a hauler is on its round trip, split between as few
+  // haulers as the spawn room can afford
+  pub fn plan_haulers(energy_per_tick: u32, round_trip: u32, energy_available: u32) -> Result<HaulerPlan,BodyError> {
+    let required_carry = (energy_per_tick * round_trip).div_ceil(screeps::constants::CARRY_CAPACITY).max(1);
+    let largest_hauler = Self::get_hauler_body(required_carry, energy_available)?
+      .into_iter()
+      .filter(|part| *part == Part::Carry)
+      .count() as u32;
+    let count = required_carry.div_ceil(largest_hauler);
+    Ok(HaulerPlan {
+      count,
+      carry_parts: required_carry.div_ceil(count),
+    })
+  }
+
+  // The first hauler keeps the original name so creeps spawned before haulers were counted are still used
+  pub fn hauler_name(source_id: RawObjectId, index: u32) -> String {
+    match index {
+      0 => String::from("hauler:") + &String::from(source_id),
+      index => format!("hauler:{}:{}", String::from(source_id), index),
+    }
+  }
+
+  fn drop_off_pos(&self, world: &dyn GameWorld) -> Result<Position,String> {
+    self.energy_drop_off.preload(world)
+      .map(|drop_off| drop_off.pos())
+      .ok_or_else(|| format!("unable to resolve energy drop off {:?}", self.energy_drop_off))
   }
 
-  fn mine_source(&self, source: Source, harvester: &Creep) -> Result<(),String> {
-    match harvester.harvest(&source) {
-      screeps::ReturnCode::Ok => Ok(()),
-      failure_code => {
-        Err(format!("Harvester {:?} unexpected return code when harvesting: {:?}", harvester.name(), failure_code))
+  // Paths are only searched once per source, a missing path falls back to the range until one is found
+  fn round_trip(&mut self, world: &dyn GameWorld, source: &SourceState) -> Result<u32,String> {
+    let source_key = String::from(source.id);
+    if let Some(round_trip) = self.round_trips.get(&source_key) {
+      return Ok(*round_trip);
+    }
+
+    // Link mined energy is collected from the receiving link instead of the source
+    let start = match self.mining_mode(source.id) {
+      MiningMode::Link { receiver, .. } => world.structure(receiver).ok_or_else(|| format!("receiving link {:?} is not visible", receiver.to_string()))?.pos,
+      MiningMode::Drop | MiningMode::Container => source.pos,
+    };
+    let drop_off_pos = self.drop_off_pos(world)?;
+    // One extra tick at each end to pick up and drop off
+    match world.find_path(start, drop_off_pos, 1) {
+      Some(path) => {
+        let round_trip = path.len() as u32 * 2 + 2;
+        self.round_trips.insert(source_key, round_trip);
+        Ok(round_trip)
       },
+      None => Ok(start.get_range_to(&drop_off_pos) * 2 + 2),
     }
   }
 
-  fn haul_energy(&self, harvester: &Creep, hauler: &Creep) -> Result<(),String> {
-    let hauler_store = hauler.store();
-    if hauler_store.get_free_capacity(Some(ResourceType::Energy)) > 0 {
-      if hauler.pos().is_near_to(harvester) {
-        // match harvester.transfer(hauler, ResourceType::Energy, None) {
-        //   ReturnCode::Ok => Ok(()),
-        //   failure_code => Err(format!("Harvester {:?} unexpected return code when transferring energy to hauler: {:?}", harvester.name(), failure_code)),
-        // }
-        match harvester.pos().look_for(look::ENERGY).get(0) {
-          Some(resource) => {
-            match hauler.pickup(resource) {
-              ReturnCode::Ok => Ok(()),
-              failure_code => Err(format!("Harvester {:?} unexpected return code when picking up energy: {:?}", harvester.name(), failure_code)),
-            }
-          },
-          None => Err(format!("Hauler {:?} no energy to pick up", hauler.name()))
-        }
-      } else {
-        match hauler.move_to(&harvester) {
+  fn find_container(world: &dyn GameWorld, source: &SourceState) -> Option<StructureState> {
+    world.find_structures(source.pos.room_name())
+      .into_iter()
+      .find(|structure| structure.structure_type == StructureType::Container && structure.pos.is_near_to(&source.pos))
+  }
+
+  fn find_container_site(world: &dyn GameWorld, source: &SourceState) -> Option<ConstructionSiteState> {
+    world.find_construction_sites(source.pos.room_name())
+      .into_iter()
+      .find(|site| site.structure_type == StructureType::Container && site.pos.is_near_to(&source.pos))
+  }
+
+  // The container, or its construction site, goes on the first tile of the path towards the drop off
+  fn container_pos(&self, world: &mut dyn GameWorld, source: &SourceState) -> Result<Position,String> {
+    if let Some(pos) = Self::find_container(world, source).map(|container| container.pos).or_else(|| Self::find_container_site(world, source).map(|site| site.pos)) {
+      return Ok(pos);
+    }
+
+    let drop_off_pos = self.drop_off_pos(world)?;
+    let access_tile = world.find_path(source.pos, drop_off_pos, 1)
+      .and_then(|path| path.first().copied())
+      .ok_or_else(|| String::from("no access tile towards the drop off"))?;
+    match world.create_construction_site(access_tile, StructureType::Container) {
+      ReturnCode::Ok => Ok(access_tile),
+      failure_code => Err(format!("unexpected return code when placing a container: {:?}", failure_code)),
+    }
+  }
+
+  // Tile next to both the source and the link that is not a wall
+  fn link_harvest_pos(world: &dyn GameWorld, source: &SourceState, link: RawObjectId) -> Result<Position,String> {
+    let link_pos = world.structure(link).ok_or_else(|| format!("link {:?} is not visible", link.to_string()))?.pos;
+    [Direction::Top, Direction::TopRight, Direction::Right, Direction::BottomRight, Direction::Bottom, Direction::BottomLeft, Direction::Left, Direction::TopLeft]
+      .into_iter()
+      .filter_map(|direction| step(source.pos, direction))
+      .find(|pos| pos.is_near_to(&link_pos) && *pos != link_pos && world.terrain(*pos) != screeps::Terrain::Wall)
+      .ok_or_else(|| format!("link {:?} shares no tile with the source", link.to_string()))
+  }
+
+  // Where the harvester has to stand and how far from that tile it may be
+  fn harvest_pos(&self, world: &mut dyn GameWorld, source: &SourceState) -> Result<(Position,u32),String> {
+    match self.mining_mode(source.id) {
+      MiningMode::Drop => Ok((source.pos, 1)),
+      MiningMode::Container => Ok((self.container_pos(world, source)?, 0)),
+      MiningMode::Link { link, .. } => Ok((Self::link_harvest_pos(world, source, link)?, 0)),
+    }
+  }
+
+  fn pickup(&self, world: &dyn GameWorld, source: &SourceState, harvester: &CreepState) -> Result<Pickup,String> {
+    match self.mining_mode(source.id) {
+      MiningMode::Drop => Ok(Pickup::Harvester(harvester.to_owned())),
+      // Until the container is built the harvester keeps its energy to build it and drops the rest
+      MiningMode::Container => Ok(Self::find_container(world, source).map_or(Pickup::Tile(harvester.pos), Pickup::Store)),
+      MiningMode::Link { receiver, .. } => world.structure(receiver)
+        .map(Pickup::Store)
+        .ok_or_else(|| format!("receiving link {:?} is not visible", receiver.to_string())),
+    }
+  }
+
+  // The hauler pulls the harvester, which has no MOVE parts, to where it harvests from
+  fn tow_to_source(&self, hive: &mut Hive, harvest_pos: (Position,u32), harvester: &CreepState, hauler: &CreepState) -> Result<(),String> {
+    let tow = GroupAction::Tow { target: harvest_pos.0, range: harvest_pos.1, path: Default::default() };
+    let group_task = GroupTask::new(vec![harvester.name.to_owned()], tow).with_owner(TaskOwner::MineRoom(self.room_name));
+    hive.tasks.add_group(hauler.name.to_owned(), group_task)
+  }
+
+  // Sources are looked at again every tick, so a task that went wrong only needs reporting
+  pub fn on_task_event(&mut self, event: &TaskEvent) {
+    if event.status != TaskStatus::Completed {
+      console::warn(format!("[ mine_room / {:?} ] Task for {:?} ended on tick {} with {:?}", self.room_name.to_string(), event.creep_name, event.time, event.status));
+    }
+  }
+
+  fn mine_source(&self, world: &mut dyn GameWorld, source: &SourceState, harvester: &CreepState) -> Result<(),String> {
+    let work_parts = harvester.count_parts(Part::Work);
+
+    // Building cancels harvesting, so the container is only worked on once there is enough energy for a full tick of it
+    if self.mining_mode(source.id) == MiningMode::Container && harvester.energy >= (work_parts * screeps::constants::BUILD_POWER).min(harvester.energy_capacity) && harvester.energy > 0 {
+      if let Some(site) = Self::find_container_site(world, source) {
+        return match world.creep_build(&harvester.name, site.id) {
           ReturnCode::Ok => Ok(()),
-          failure_code => Err(format!("Hauler {:?} unexpected return code when approaching pickup: {:?}", hauler.name(), failure_code)),
+          failure_code => Err(format!("Harvester {:?} unexpected return code when building a container: {:?}", harvester.name, failure_code)),
+        };
+      }
+    }
+
+    match world.creep_harvest(&harvester.name, source.id) {
+      ReturnCode::Ok => {},
+      failure_code => return Err(format!("Harvester {:?} unexpected return code when harvesting: {:?}", harvester.name, failure_code)),
+    }
+
+    if let MiningMode::Link { link, receiver } = self.mining_mode(source.id) {
+      // Emptied before the next harvest would overflow
+      if harvester.energy > 0 && harvester.free_capacity() <= work_parts * screeps::constants::HARVEST_POWER {
+        match world.creep_transfer(&harvester.name, link, ResourceType::Energy, None) {
+          ReturnCode::Ok | ReturnCode::Full => {},
+          failure_code => return Err(format!("Harvester {:?} unexpected return code when filling link: {:?}", harvester.name, failure_code)),
+        }
+      }
+      // Sent in large batches, every transfer puts the link on cooldown
+      if world.structure(link).map_or(false, |link| link.energy * 2 >= link.energy_capacity) {
+        match world.link_transfer_energy(link, receiver, None) {
+          ReturnCode::Ok | ReturnCode::Tired | ReturnCode::Full => {},
+          failure_code => return Err(format!("Link {:?} unexpected return code when sending energy: {:?}", link.to_string(), failure_code)),
         }
       }
+    }
+
+    Ok(())
+  }
+
+  fn collect_energy(&self, world: &mut dyn GameWorld, pickup: &Pickup, hauler: &CreepState) -> Result<(),String> {
+    let (pickup_pos, store) = match pickup {
+      Pickup::Harvester(harvester) => (harvester.pos, None),
+      Pickup::Tile(pos) => (*pos, None),
+      Pickup::Store(store) => (store.pos, Some(store)),
+    };
+    if !hauler.pos.is_near_to(&pickup_pos) {
+      return match world.creep_move_to(&hauler.name, pickup_pos) {
+        ReturnCode::Ok => Ok(()),
+        failure_code => Err(format!("Hauler {:?} unexpected return code when approaching pickup: {:?}", hauler.name, failure_code)),
+      };
+    }
+
+    let hauler_id = hauler.id.ok_or_else(|| format!("Hauler {:?} has no id", hauler.name))?;
+    let mut collected = false;
+    match (pickup, store) {
+      (Pickup::Harvester(harvester), _) if harvester.energy > 0 => {
+        match world.creep_transfer(&harvester.name, hauler_id, ResourceType::Energy, None) {
+          ReturnCode::Ok => collected = true,
+          failure_code => return Err(format!("Harvester {:?} unexpected return code when transferring energy to hauler: {:?}", harvester.name, failure_code)),
+        }
+      },
+      (_, Some(store)) if store.energy > 0 => {
+        match world.creep_withdraw(&hauler.name, store.id, ResourceType::Energy, None) {
+          ReturnCode::Ok => collected = true,
+          failure_code => return Err(format!("Hauler {:?} unexpected return code when withdrawing energy: {:?}", hauler.name, failure_code)),
+        }
+      },
+      _ => {},
+    }
+    // Whatever overflowed onto the pickup tile
+    if let Some(resource) = world.look_for_energy(pickup_pos).first() {
+      match world.creep_pickup(&hauler.name, resource.id) {
+        ReturnCode::Ok => collected = true,
+        failure_code => return Err(format!("Hauler {:?} unexpected return code when picking up energy: {:?}", hauler.name, failure_code)),
+      }
+    }
+
+    if collected {
+      Ok(())
+    } else {
+      Err(format!("Hauler {:?} no energy to pick up", hauler.name))
+    }
+  }
+
+  fn haul_energy(&self, world: &mut dyn GameWorld, pickup: &Pickup, hauler: &CreepState) -> Result<(),String> {
+    if hauler.free_capacity() > 0 {
+      self.collect_energy(world, pickup, hauler)
     } else {
-      match self.energy_drop_off.preload() {
+      match self.energy_drop_off.preload(world) {
         Some(drop_off) => {
           let drop_off_pos = drop_off.pos();
-          if hauler.pos().is_near_to(&drop_off_pos) {
-            drop_off.accept_energy(hauler, None)
+          if hauler.pos.is_near_to(&drop_off_pos) {
+            drop_off.accept_energy(world, &hauler.name, None)
               .map(|_| ())
-              .map_err(|e| format!("Hauler {:?} unexpected return code when dropping off: {:?}", hauler.name(), e))
+              .map_err(|e| format!("Hauler {:?} unexpected return code when dropping off: {:?}", hauler.name, e))
           } else {
-            match hauler.move_to(drop_off_pos) {
+            match world.creep_move_to(&hauler.name, drop_off_pos) {
               ReturnCode::Ok => Ok(()),
-              failure_code => Err(format!("Hauler {:?} unexpected return code when approaching dropoff: {:?}", hauler.name(), failure_code)),
+              failure_code => Err(format!("Hauler {:?} unexpected return code when approaching dropoff: {:?}", hauler.name, failure_code)),
             }
           }
         },
-        None => Err(format!("Hauler {:?} unable to resolve energy drop off {:?}", hauler.name(), self.energy_drop_off))
+        None => Err(format!("Hauler {:?} unable to resolve energy drop off {:?}", hauler.name, self.energy_drop_off))
       }
     }
   }
 
-  fn process_source(&self, source: Source, hive: &mut Hive) -> Result<(),String> {
-    let hauler_name = String::from("hauler:") + &String::from(source.raw_id());
-    let hauler_result = hive.get_creep(hauler_name, &self.spawn_room_name, false, Self::get_hauler_body);
+  // Spawning takes CREEP_SPAWN_TIME ticks per part, and the new creep then has to get from the spawn to the source. The
+  // travel time is taken as half the round trip, towed harvesters can take longer.
+  fn lead_time(body: Result<Vec<Part>,BodyError>, round_trip: u32) -> u32 {
+    body.map_or(0, |body| body.len() as u32 * screeps::constants::CREEP_SPAWN_TIME) + round_trip / 2
+  }
 
-    let harvester_name = String::from("harvester:") + &String::from(source.raw_id());
-    let harvester = hive.get_creep(harvester_name, &self.spawn_room_name, false, Self::get_harvester_body)?;
+  fn process_source(&mut self, world: &mut dyn GameWorld, source: &SourceState, hive: &mut Hive) -> Result<(),String> {
+    let harvester_role = String::from("harvester:") + &String::from(source.id);
 
-    if harvester.pos().is_near_to(&source) {
-      self.mine_source(source, &harvester)?;
-      self.haul_energy(&harvester, &hauler_result?)?;
-    } else {
-      self.tow_to_source(source, &harvester, &hauler_result?)?;
+    let energy_available = hive.spawn_rooms.get(&self.spawn_room_name)
+      .map(|spawn_room| spawn_room.max_energy(world))
+      .ok_or(GetCreepError::NoSpawnAvailable)?;
+    let energy_per_tick = source.energy_capacity.div_ceil(screeps::constants::ENERGY_REGEN_TIME);
+    let round_trip = self.round_trip(world, source)?;
+    let hauler_plan = Self::plan_haulers(energy_per_tick, round_trip, energy_available)?;
+    let harvester_carry_parts = Self::harvester_carry_parts(self.mining_mode(source.id), energy_per_tick, round_trip, hauler_plan, energy_available);
+    let hauler_lead_time = Self::lead_time(Self::get_hauler_body(hauler_plan.carry_parts, energy_available), round_trip);
+    let harvester_lead_time = Self::lead_time(Self::get_harvester_body(harvester_carry_parts, energy_available), round_trip);
+
+    // A source earns nothing without its harvester, but a harvester cannot reach the source until a hauler tows it
+    let hauler_priority = if role_names(&harvester_role).iter().all(|name| world.creep(name).is_none()) { SpawnPriority::High } else { SpawnPriority::Normal };
+    let mut haulers = vec![];
+    let mut hauler_errors = vec![];
+    for index in 0..hauler_plan.count {
+      match hive.get_role_creeps(world, &Self::hauler_name(source.id, index), &self.spawn_room_name, hauler_priority, hauler_lead_time, |energy| Self::get_hauler_body(hauler_plan.carry_parts, energy)) {
+        Ok(role_haulers) => haulers.extend(role_haulers),
+        // Waiting on the spawn or on another task is how haulers normally spend part of their time
+        Err(GetCreepError::Queued(_) | GetCreepError::SpawningInProgress | GetCreepError::CreepBusy) => {},
+        Err(error) => hauler_errors.push(String::from(error)),
+      }
     }
+    // Haulers left over from a larger plan keep working until they die
+    haulers.extend(
+      (hauler_plan.count..)
+        .map_while(|index| {
+          let role_haulers = role_names(&Self::hauler_name(source.id, index)).iter().filter_map(|name| world.creep(name)).collect::<Vec<_>>();
+          if role_haulers.is_empty() { None } else { Some(role_haulers) }
+        })
+        .flatten()
+        .filter(|hauler| !hauler.spawning && !hive.tasks.has_task(&hauler.name))
+    );
 
-    Ok(())
+    let harvesters = match hive.get_role_creeps(world, &harvester_role, &self.spawn_room_name, SpawnPriority::High, harvester_lead_time, |energy| Self::get_harvester_body(harvester_carry_parts, energy)) {
+      Err(GetCreepError::CreepBusy | GetCreepError::Queued(_) | GetCreepError::SpawningInProgress) => vec![], // Still being towed or spawned
+      harvesters => harvesters?,
+    };
+
+    let mut failures = hauler_errors;
+    if !harvesters.is_empty() {
+      let harvest_pos = self.harvest_pos(world, source)?;
+      let in_range = |creep: &CreepState, (pos, range): (Position, u32)| creep.pos.room_name() == pos.room_name() && creep.pos.get_range_to(&pos) <= range;
+      let (working, waiting): (Vec<_>,Vec<_>) = harvesters.into_iter().partition(|harvester| in_range(harvester, harvest_pos));
+      for harvester in working.iter() {
+        self.mine_source(world, source, harvester)?;
+      }
+
+      // A successor waits next to a tile that the harvester it replaces is still standing on. Without a free hauler to
+      // tow it, it waits for the next tick.
+      if let Some(harvester) = waiting.first() {
+        let target = if harvest_pos.1 == 0 && !working.is_empty() { (harvest_pos.0, 1) } else { harvest_pos };
+        if !in_range(harvester, target) && !haulers.is_empty() {
+          self.tow_to_source(hive, target, harvester, &haulers.remove(0))?;
+        }
+      }
+
+      if let Some(harvester) = working.first() {
+        let pickup = self.pickup(world, source, harvester)?;
+        failures.extend(haulers.iter().filter_map(|hauler| self.haul_energy(world, &pickup, hauler).err()));
+      }
+    }
+
+    if failures.is_empty() {
+      Ok(())
+    } else {
+      Err(failures.join(", "))
+    }
   }
 
-  pub fn run(&self,hive: &mut Hive) {
-    for source_id in self.source_ids.iter() {
-      let source = ObjectId::<Source>::from(source_id.to_owned()).resolve().unwrap();
-      if let Err(failure_reason) = self.process_source(source, hive) {
-        console::warn(format!("[ mine_room / {:?} ] Failed to process source {:?} because {:?}", self.room_name.to_string(), ObjectId::<Source>::from(source_id.to_owned()).to_string(), failure_reason));
+  pub fn run(&mut self, hive: &mut Hive, world: &mut dyn GameWorld) {
+    for source_id in self.source_ids.to_owned() {
+      let result = match world.source(source_id) {
+        Some(source) => self.process_source(world, &source, hive),
+        None => Err(String::from("source is not visible")),
+      };
+      if let Err(failure_reason) = result {
+        console::warn(format!("[ mine_room / {:?} ] Failed to process source {:?} because {:?}", self.room_name.to_string(), source_id.to_string(), failure_reason));
       }
     }
   }
-}
\ No newline at end of file
+}
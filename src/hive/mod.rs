@@ -1,27 +1,32 @@
 use std::{collections::HashMap};
 
-use screeps::{game, Room, ReturnCode, Creep, Part, RoomName};
+use screeps::{Part, RoomName};
 use serde::{Serialize, Deserialize};
 
+pub mod body_planner;
 pub mod energy_distributer;
+pub mod group_tasks;
 pub mod id_generator;
 pub mod mine_room;
 pub mod tasks;
 pub mod spawn_room;
 
 // use id_generator::IdGenerator;
+use body_planner::BodyError;
 use mine_room::MineRoom;
-use tasks::Tasks;
-use spawn_room::SpawnRoom;
+use tasks::{TaskOwner, Tasks};
+use spawn_room::{SpawnPriority, SpawnRoom};
 
 use self::energy_distributer::EnergyDropOffLoaded;
+use crate::world::{GameWorld, CreepState};
 
 #[derive(Debug, Clone)]
 pub enum GetCreepError {
   CreepBusy,
   NoSpawnAvailable,
   SpawningInProgress,
-  SpawningFailed(ReturnCode),
+  Queued(usize), // Place in the spawn room's queue, 0 spawns next
+  InvalidBody(BodyError), // No body fits the spawn room's energy
 }
 
 impl From<GetCreepError> for String {
@@ -30,6 +35,14 @@ impl From<GetCreepError> for String {
   }
 }
 
+// A role is held by a creep named after it, or by one named after it with SUCCESSOR_SUFFIX, so that a replacement can
+// be spawned while the creep it replaces is still alive
+pub const SUCCESSOR_SUFFIX: &str = "+";
+
+pub fn role_names(role: &str) -> [String;2] {
+  [role.to_owned(), format!("{}{}", role, SUCCESSOR_SUFFIX)]
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Hive {
   // pub id_generator: IdGenerator,
@@ -41,18 +54,18 @@ pub struct Hive {
 }
 
 impl Hive {
-  pub fn new(room: Room) -> Self {
+  pub fn new(world: &dyn GameWorld, room_name: RoomName) -> Self {
     // let mut id_generator = IdGenerator::new();
 
-    let initial_spawn_group = SpawnRoom::new(&room);
-    let energy_drop_off = EnergyDropOffLoaded::Spawn(initial_spawn_group.available_spawn().unwrap());
-    let initial_mine_group = MineRoom::new(&room,room.name(),energy_drop_off);
+    let initial_spawn_group = SpawnRoom::new(world, room_name);
+    let energy_drop_off = EnergyDropOffLoaded::Spawn(initial_spawn_group.available_spawn(world).unwrap());
+    let initial_mine_group = MineRoom::new(world, room_name, room_name, energy_drop_off);
 
     let mut spawn_rooms = HashMap::new();
-    spawn_rooms.insert(room.name(),initial_spawn_group);
+    spawn_rooms.insert(room_name,initial_spawn_group);
 
     let mut mine_rooms = HashMap::new();
-    mine_rooms.insert(room.name(),initial_mine_group);
+    mine_rooms.insert(room_name,initial_mine_group);
 
     Self {
       // id_generator,
@@ -64,37 +77,89 @@ impl Hive {
     }
   }
 
-  pub fn get_creep<C>(&self, creep_name: String, spawn_room_name: &RoomName, urgent: bool, mut calculate_body: C) -> Result<Creep,GetCreepError>
-  where C: FnMut(u32) -> Vec<Part> {
+  // Missing creeps are only queued, the spawn room decides what to spawn once every subsystem has asked
+  pub fn get_creep<C>(&mut self, world: &mut dyn GameWorld, creep_name: String, spawn_room_name: &RoomName, priority: SpawnPriority, mut calculate_body: C) -> Result<CreepState,GetCreepError>
+  where C: FnMut(u32) -> Result<Vec<Part>,BodyError> {
     if self.tasks.has_task(&creep_name) {
       return Err(GetCreepError::CreepBusy)
     }
 
-    if let Some(creep) = game::creeps().get(creep_name.to_owned()) {
-      if !creep.spawning() {
+    if let Some(creep) = world.creep(&creep_name) {
+      if !creep.spawning {
         return Ok(creep);
       } else {
         return Err(GetCreepError::SpawningInProgress);
       }
     }
 
-    if let Some(spawn) = self.spawn_rooms.get(spawn_room_name).map(|spawn_group| spawn_group.available_spawn()).unwrap_or(None) {
-      let energy = if urgent { spawn.room().unwrap().energy_available() } else { spawn.room().unwrap().energy_capacity_available() };
-      let creep_body = calculate_body(energy);
-      match spawn.spawn_creep(&creep_body, &creep_name) {
-        ReturnCode::Ok => Err(GetCreepError::SpawningInProgress),
-        unexpected => Err(GetCreepError::SpawningFailed(unexpected)),
-      }
-    } else {
-      Err(GetCreepError::NoSpawnAvailable)
+    match self.spawn_rooms.get_mut(spawn_room_name) {
+      Some(spawn_room) if !spawn_room.spawn_ids.is_empty() => {
+        let energy = if priority == SpawnPriority::Critical { spawn_room.available_energy(world) } else { spawn_room.max_energy(world) };
+        let creep_body = calculate_body(energy).map_err(GetCreepError::InvalidBody)?;
+        Err(GetCreepError::Queued(spawn_room.request_spawn(world, &creep_name, creep_body, priority)))
+      },
+      _ => Err(GetCreepError::NoSpawnAvailable),
+    }
+  }
+
+  // Creeps holding the role that are free to work, the one that dies first listed first. A successor is queued under the
+  // unused name once every creep holding the role has `lead_time` ticks or less to live.
+  pub fn get_role_creeps<C>(&mut self, world: &mut dyn GameWorld, role: &str, spawn_room_name: &RoomName, priority: SpawnPriority, lead_time: u32, calculate_body: C) -> Result<Vec<CreepState>,GetCreepError>
+  where C: FnMut(u32) -> Result<Vec<Part>,BodyError> {
+    let names = role_names(role);
+    let mut holders = names.iter().filter_map(|name| world.creep(name)).collect::<Vec<_>>();
+    // Creeps that are still spawning have no ticks to live yet
+    holders.sort_by_key(|creep| creep.ticks_to_live.unwrap_or(u32::MAX));
+
+    let expiring = holders.iter().all(|creep| creep.ticks_to_live.map_or(false, |ticks_to_live| ticks_to_live <= lead_time));
+    let unused_name = names.iter().find(|name| !holders.iter().any(|creep| creep.name == **name));
+    let spawn_error = match unused_name {
+      Some(unused_name) if expiring => self.get_creep(world, unused_name.to_owned(), spawn_room_name, priority, calculate_body).err(),
+      _ => None,
+    };
+
+    let busy = holders.iter().any(|creep| self.tasks.has_task(&creep.name));
+    let available = holders.into_iter()
+      .filter(|creep| !creep.spawning && !self.tasks.has_task(&creep.name))
+      .collect::<Vec<_>>();
+    match spawn_error {
+      _ if !available.is_empty() => Ok(available),
+      Some(spawn_error) => Err(spawn_error),
+      None if busy => Err(GetCreepError::CreepBusy),
+      None => Err(GetCreepError::SpawningInProgress),
     }
   }
 
-  pub fn run(&mut self) {
-    for mine_group in self.mine_rooms.to_owned().values() {
-      mine_group.run(self);
+  pub fn run(&mut self, world: &mut dyn GameWorld) {
+    // Owners that have since been removed never hear about their tasks
+    for event in self.tasks.take_events() {
+      match &event.owner {
+        TaskOwner::MineRoom(room_name) => if let Some(mine_room) = self.mine_rooms.get_mut(room_name) {
+          mine_room.on_task_event(&event);
+        },
+        TaskOwner::SpawnRoom(room_name) => if let Some(spawn_room) = self.spawn_rooms.get_mut(room_name) {
+          spawn_room.on_task_event(&event);
+        },
+      }
+    }
+
+    // Rooms run in name order so that a replay of the tick issues its intents in the same order. Each mine room is
+    // taken out of the hive while it runs so that it can update itself.
+    let mut mine_room_names = self.mine_rooms.keys().copied().collect::<Vec<_>>();
+    mine_room_names.sort_by_key(|room_name| room_name.to_string());
+    for room_name in mine_room_names {
+      if let Some(mut mine_room) = self.mine_rooms.remove(&room_name) {
+        mine_room.run(self, world);
+        self.mine_rooms.insert(room_name, mine_room);
+      }
+    }
+
+    let mut spawn_rooms = self.spawn_rooms.values_mut().collect::<Vec<_>>();
+    spawn_rooms.sort_by_key(|spawn_room| spawn_room.room_name.to_string());
+    for spawn_room in spawn_rooms {
+      spawn_room.run(world);
     }
 
-    self.tasks.run();
+    self.tasks.run(world);
   }
 }
\ No newline at end of file
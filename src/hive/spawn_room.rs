@@ -1,14 +1,37 @@
-use screeps::{HasId, StructureObject, ObjectId, RawObjectId, StructureSpawn, StructureExtension, Room, find, RoomName, game};
+use screeps::{Part, RawObjectId, ReturnCode, RoomName, StructureType};
 use serde::{Serialize, Deserialize};
 
 use std::{cell::RefCell};
 
-#[derive(Debug, Clone)]
+use crate::console;
+use crate::constants::SPAWN_REQUEST_TIMEOUT;
+use crate::world::{GameWorld, StructureState};
+use super::tasks::{TaskEvent, TaskStatus};
+
+#[derive(Debug, Clone, Default)]
 struct CachedSpawnList(u32,Option<RefCell<Vec<RawObjectId>>>);
 
-impl Default for CachedSpawnList {
-  fn default() -> Self {
-    Self(game::time(), None)
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SpawnPriority {
+  Low,
+  Normal,
+  High,
+  Critical, // Spawned with whatever energy is available instead of waiting for a full room
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SpawnRequest {
+  pub name: String,
+  pub body: Vec<Part>,
+  pub priority: SpawnPriority,
+  pub requested: u32, // Tick it was first asked for
+  pub updated: u32, // Tick it was last asked for
+  pub dispatched: Option<u32>, // Tick the spawn was ordered on, the request stays queued until the creep shows up
+}
+
+impl SpawnRequest {
+  pub fn cost(&self) -> u32 {
+    self.body.iter().map(|part| part.cost()).sum()
   }
 }
 
@@ -19,79 +42,178 @@ pub struct SpawnRoom {
   pub spawn_ids: Vec<RawObjectId>, // RawObjectId == ObjectId<StructureSpawn>
   pub extension_ids: Vec<RawObjectId>, // RawObjectId == ObjectId<StructureExtension>
 
+  // Highest priority first, then oldest first. Only the first request that cannot be afforded holds back the rest, so
+  // cheaper requests behind it never spend the energy it is waiting for.
+  pub spawn_queue: Vec<SpawnRequest>,
+
   #[serde(skip)]
   available_spawn_cache: RefCell<CachedSpawnList>,
 }
 
 impl SpawnRoom {
-  pub fn new(room: &Room) -> Self {
+  pub fn new(world: &dyn GameWorld, room_name: RoomName) -> Self {
     let mut spawns = vec![];
     let mut extensions = vec![];
 
-    for structure in room.find(find::MY_STRUCTURES) {
-      match structure {
-        StructureObject::StructureSpawn(spawn) => spawns.insert(0,spawn),
-        StructureObject::StructureExtension(extension) => extensions.insert(0,extension),
+    for structure in world.find_my_structures(room_name) {
+      match structure.structure_type {
+        StructureType::Spawn => spawns.insert(0,structure),
+        StructureType::Extension => extensions.insert(0,structure),
         _ => {},
       }
     }
 
     Self {
-      room_name: room.name(),
+      room_name,
+
+      spawn_ids: spawns.iter().map(|spawn| spawn.id).collect(),
+      extension_ids: extensions.iter().map(|extension| extension.id).collect(),
 
-      spawn_ids: spawns.iter().map(|spawn| spawn.raw_id()).collect(),
-      extension_ids: extensions.iter().map(|extension| extension.raw_id()).collect(),
+      spawn_queue: vec![],
 
       available_spawn_cache: RefCell::new(CachedSpawnList::default()),
     }
   }
 
-  pub fn get_spawns(&self) -> Vec<StructureSpawn> {
-    self.spawn_ids.iter().map(|spawn_id| ObjectId::<StructureSpawn>::from(spawn_id.to_owned()).resolve().unwrap()).collect()
+  pub fn get_spawns(&self, world: &dyn GameWorld) -> Vec<StructureState> {
+    self.spawn_ids.iter().filter_map(|spawn_id| world.structure(spawn_id.to_owned())).collect()
   }
 
-  pub fn get_extensions(&self) -> Vec<StructureExtension> {
-    self.extension_ids.iter().map(|extension_id| ObjectId::<StructureExtension>::from(extension_id.to_owned()).resolve().unwrap()).collect()
+  pub fn get_extensions(&self, world: &dyn GameWorld) -> Vec<StructureState> {
+    self.extension_ids.iter().filter_map(|extension_id| world.structure(extension_id.to_owned())).collect()
   }
 
-  pub fn available_energy(&self) -> u32 {
-    if let Some(room) = game::rooms().get(self.room_name) {
-      room.energy_available()
+  pub fn available_energy(&self, world: &dyn GameWorld) -> u32 {
+    if let Some(room) = world.room(self.room_name) {
+      room.energy_available
     } else {
       0
     }
   }
 
-  pub fn max_energy(&self) -> u32 {
-    if let Some(room) = game::rooms().get(self.room_name) {
-      room.energy_capacity_available()
+  pub fn max_energy(&self, world: &dyn GameWorld) -> u32 {
+    if let Some(room) = world.room(self.room_name) {
+      room.energy_capacity_available
     } else {
       0
     }
   }
 
-  pub fn available_spawn(&self) -> Option<StructureSpawn> {
+  pub fn available_spawn(&self, world: &dyn GameWorld) -> Option<StructureState> {
     let mut cache = self.available_spawn_cache.borrow_mut();
-    if cache.0 != game::time() {
-      *cache = CachedSpawnList::default();
+    if cache.0 != world.time() {
+      *cache = CachedSpawnList(world.time(), None);
     }
     if cache.1.is_none() {
-      let spawn_id_list = self.get_spawns().into_iter()
-        .filter(|spawn| spawn.spawning().is_none())
-        .map(|spawn| spawn.raw_id())
+      let spawn_id_list = self.get_spawns(world).into_iter()
+        .filter(|spawn| !spawn.spawning)
+        .map(|spawn| spawn.id)
         .collect::<Vec<_>>();
       cache.1 = Some(RefCell::new(spawn_id_list));
     }
     match cache.1.as_ref() {
       Some(available_spawns) => {
         let selected_spawn = available_spawns.borrow_mut().pop();
-        selected_spawn.and_then(|spawn_id| ObjectId::<StructureSpawn>::from(spawn_id).resolve())
+        selected_spawn.and_then(|spawn_id| world.structure(spawn_id))
       },
       None => None,
     }
   }
 
-  pub fn add_spawn(&mut self, spawn_id: ObjectId<StructureSpawn>) {
-    self.spawn_ids.insert(0, spawn_id.into());
+  // Asking again for a creep that is already queued only updates its body and priority. Returns its place in the queue.
+  pub fn request_spawn(&mut self, world: &dyn GameWorld, name: &str, body: Vec<Part>, priority: SpawnPriority) -> usize {
+    let time = world.time();
+    match self.spawn_queue.iter_mut().find(|request| request.name == name) {
+      Some(request) => {
+        request.body = body;
+        request.priority = priority;
+        request.updated = time;
+      },
+      None => self.spawn_queue.push(SpawnRequest {
+        name: name.to_owned(),
+        body,
+        priority,
+        requested: time,
+        updated: time,
+        dispatched: None,
+      }),
+    }
+    self.spawn_queue.sort_by(|a, b| b.priority.cmp(&a.priority).then(a.requested.cmp(&b.requested)));
+    self.spawn_queue.iter().position(|request| request.name == name).unwrap_or(0)
+  }
+
+  pub fn pending_requests(&self) -> &[SpawnRequest] {
+    &self.spawn_queue
+  }
+
+  // Starts as many queued creeps as there are free spawns and energy for, in queue order. A spawn the game accepted is
+  // only an intent, so the request is kept until its creep is seen and queued again if it never appears.
+  pub fn run(&mut self, world: &mut dyn GameWorld) {
+    let time = world.time();
+    let mut spawned = vec![];
+    for request in self.spawn_queue.iter_mut() {
+      if let Some(dispatched) = request.dispatched {
+        if world.creep(&request.name).is_some() {
+          spawned.push(request.name.to_owned());
+        } else if dispatched < time {
+          console::warn(format!("[ spawn_room / {:?} ] {:?} was ordered on tick {} but never appeared, queuing it again", self.room_name.to_string(), request.name, dispatched));
+          request.dispatched = None;
+        }
+      }
+    }
+
+    let max_energy = self.max_energy(world);
+    self.spawn_queue.retain(|request| {
+      let stale = time.saturating_sub(request.updated) > SPAWN_REQUEST_TIMEOUT;
+      let unaffordable = request.cost() > max_energy;
+      if unaffordable {
+        console::warn(format!("[ spawn_room / {:?} ] Dropping {:?}, its body costs {} but the room holds at most {}", self.room_name.to_string(), request.name, request.cost(), max_energy));
+      }
+      !stale && !unaffordable && !spawned.contains(&request.name)
+    });
+
+    let mut energy = self.available_energy(world);
+    let mut finished = vec![]; // Spawned under that name already, or never going to be
+    let mut dispatched = vec![];
+    for request in self.spawn_queue.iter() {
+      // Its energy was already taken on the tick it was ordered
+      if request.dispatched.is_some() {
+        continue;
+      }
+      if request.cost() > energy {
+        break;
+      }
+      let spawn = match self.available_spawn(world) {
+        Some(spawn) => spawn,
+        None => break,
+      };
+      match world.spawn_creep(spawn.id, &request.body, &request.name) {
+        ReturnCode::Ok => {
+          energy -= request.cost();
+          dispatched.push(request.name.to_owned());
+        },
+        ReturnCode::NameExists => finished.push(request.name.to_owned()),
+        ReturnCode::NotEnough | ReturnCode::Busy => break,
+        ReturnCode::InvalidArgs => {
+          console::warn(format!("[ spawn_room / {:?} ] Dropping {:?}, its body can never be spawned", self.room_name.to_string(), request.name));
+          finished.push(request.name.to_owned());
+        },
+        unexpected => console::warn(format!("[ spawn_room / {:?} ] Unable to spawn {:?}: {:?}", self.room_name.to_string(), request.name, unexpected)),
+      }
+    }
+    self.spawn_queue.retain(|request| !finished.contains(&request.name));
+    for request in self.spawn_queue.iter_mut().filter(|request| dispatched.contains(&request.name)) {
+      request.dispatched = Some(time);
+    }
+  }
+
+  pub fn on_task_event(&mut self, event: &TaskEvent) {
+    if event.status != TaskStatus::Completed {
+      console::warn(format!("[ spawn_room / {:?} ] Task for {:?} ended on tick {} with {:?}", self.room_name.to_string(), event.creep_name, event.time, event.status));
+    }
   }
-}
\ No newline at end of file
+
+  pub fn add_spawn(&mut self, spawn_id: RawObjectId) {
+    self.spawn_ids.insert(0, spawn_id);
+  }
+}
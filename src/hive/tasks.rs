@@ -1,77 +1,383 @@
 use std::collections::{VecDeque, HashMap};
+use std::fmt;
 
-use screeps::{ReturnCode, Direction, ObjectId, Creep, Source, RawObjectId, game};
+use screeps::{ReturnCode, Direction, Position, RawObjectId, ResourceType, RoomName};
 use serde::{Serialize, Deserialize};
 
+use crate::console;
+use crate::constants::{MOVE_STUCK_TICKS, TASK_RETRIES};
+use super::group_tasks::GroupTask;
+use crate::world::{CreepState, GameWorld, IntentSlot, digit_direction, direction_digit, path_directions, step_along};
+
 pub enum TaskReturn {
   Complete,
   ProgressMade,
-  Err(ReturnCode),
+  Err(TaskError),
+}
+
+// What a task was aimed at
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum TaskTarget {
+  Object(RawObjectId),
+  Position(Position),
+  Creep(String),
+}
+
+// Why a task failed and where, the path runs from the outermost task down to the step that failed
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TaskError {
+  pub path: Vec<String>,
+  pub target: Option<TaskTarget>, // Of the innermost task that had one
+  pub return_codes: Vec<ReturnCode>, // Every code the failing step got back, some steps issue more than one intent
+  pub time: u32,
+}
+
+impl TaskError {
+  pub fn new(return_code: ReturnCode) -> Self {
+    Self::from_codes(vec![return_code])
+  }
+
+  pub fn from_codes(return_codes: Vec<ReturnCode>) -> Self {
+    Self {
+      path: vec![],
+      target: None,
+      return_codes,
+      time: 0,
+    }
+  }
+
+  // Adds the context of a task the error passes through on its way out
+  pub fn within(mut self, step: &str, target: Option<TaskTarget>, time: u32) -> Self {
+    self.path.insert(0, step.to_owned());
+    if self.target.is_none() {
+      self.target = target;
+    }
+    self.time = time;
+    self
+  }
+
+  // The first code that was not Ok
+  pub fn return_code(&self) -> ReturnCode {
+    self.return_codes.iter().copied().find(|return_code| *return_code != ReturnCode::Ok).unwrap_or(ReturnCode::Ok)
+  }
+
+  pub fn failure(&self) -> Failure {
+    Failure::classify(self.return_code())
+  }
+}
+
+impl fmt::Display for TaskError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{} failed with {:?} on tick {}", self.path.join(" > "), self.return_codes, self.time)?;
+    match &self.target {
+      Some(target) => write!(f, " targeting {:?}", target),
+      None => Ok(()),
+    }
+  }
+}
+
+// The rest of a path found for `Task::MoveTo`, kept in the task so it is only searched for again when the creep
+// strays from it or gets stuck
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct TravelPath {
+  pub from: Option<Position>, // Where the creep stood when it was sent along the first remaining direction
+  pub directions: String, // One digit per step, numbered like Direction
+  pub stuck: u32,
+}
+
+impl TravelPath {
+  // Drops the step the creep has just taken, or the whole path when the creep is no longer on it
+  fn advance(&mut self, pos: Position, fatigue: u32) {
+    let first_direction = self.directions.chars().next().and_then(digit_direction);
+    match (self.from, first_direction) {
+      (Some(from), Some(_)) if from == pos => {
+        if fatigue == 0 {
+          self.stuck += 1;
+        }
+      },
+      (Some(from), Some(direction)) if step_along(from, direction) == pos => {
+        self.directions.remove(0);
+        self.from = Some(pos);
+        self.stuck = 0;
+      },
+      _ => *self = Self::default(),
+    }
+  }
+
+  // Sends the creep one step further along, searching for a new path when there is none left or it is stuck
+  pub(crate) fn travel(&mut self, world: &mut dyn GameWorld, creep: &CreepState, target: Position, range: u32) -> TaskReturn {
+    self.advance(creep.pos, creep.fatigue);
+    if self.directions.is_empty() || self.stuck >= MOVE_STUCK_TICKS {
+      let directions = match world.find_path(creep.pos, target, range) {
+        Some(found) => path_directions(creep.pos, &found),
+        None => return TaskReturn::Err(TaskError::new(ReturnCode::NoPath)),
+      };
+      *self = TravelPath {
+        from: Some(creep.pos),
+        directions: directions.into_iter().map(direction_digit).collect(),
+        stuck: 0,
+      };
+    }
+
+    // The game accepts a move into an obstacle, so standing still without being tired is not progress either
+    let stalled = self.stuck > 0;
+    match self.directions.chars().next().and_then(digit_direction) {
+      Some(direction) => match world.creep_move_direction(&creep.name, direction) {
+        ReturnCode::Ok | ReturnCode::Tired if stalled => TaskReturn::Err(TaskError::new(ReturnCode::NoPath)),
+        ReturnCode::Ok | ReturnCode::Tired => TaskReturn::ProgressMade,
+        return_code => TaskReturn::Err(TaskError::new(return_code)),
+      },
+      None => TaskReturn::Err(TaskError::new(ReturnCode::NoPath)),
+    }
+  }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum Task {
   Move(Direction),
+  MoveTo { target: Position, range: u32, #[serde(default)] path: TravelPath },
   Tow(String,Direction), // String == Valid creep name
   Harvest(RawObjectId), // RawObjectId == ObjectId<Source>
+  Transfer { target: RawObjectId, resource_type: ResourceType, amount: Option<u32> }, // None == as much as possible
+  Withdraw { target: RawObjectId, resource_type: ResourceType, amount: Option<u32> },
+  Pickup(RawObjectId), // RawObjectId == ObjectId<Resource>
+  Drop { resource_type: ResourceType, amount: Option<u32> },
+  Build(RawObjectId), // RawObjectId == ObjectId<ConstructionSite>
+  Repair(RawObjectId), // RawObjectId == ObjectId<Structure>
+  UpgradeController(RawObjectId), // RawObjectId == ObjectId<StructureController>
+  Dismantle(RawObjectId), // RawObjectId == ObjectId<Structure>
+  ClaimController(RawObjectId), // RawObjectId == ObjectId<StructureController>
+  ReserveController(RawObjectId), // RawObjectId == ObjectId<StructureController>
+  Heal(RawObjectId), // RawObjectId == ObjectId<Creep>
+  Attack(RawObjectId), // RawObjectId == ObjectId<Creep> or ObjectId<Structure>
+  RangedAttack(RawObjectId), // RawObjectId == ObjectId<Creep> or ObjectId<Structure>
   Continuous(Box<Task>),
   Perpetual(Box<Task>),
   MultiStep(VecDeque<Box<Task>>),
+  // Loops run a fresh copy of `task` for every iteration, the copy in progress is kept in `progress`
+  Until { condition: Condition, task: Box<Task>, #[serde(default)] progress: Option<Box<Task>> },
+  While { condition: Condition, task: Box<Task>, #[serde(default)] progress: Option<Box<Task>> },
+  Repeat { times: u32, task: Box<Task>, #[serde(default)] completed: u32, #[serde(default)] progress: Option<Box<Task>> },
+  // The condition is checked once, the chosen branch then runs to the end
+  IfElse { condition: Condition, then: Box<Task>, otherwise: Box<Task>, #[serde(default)] chosen: Option<bool> },
+  // Runs every task whose intents can be carried out alongside those of the tasks before it, the rest wait for a
+  // later tick. Finished tasks are dropped, it completes once they all have.
+  Simultaneous(Vec<Box<Task>>),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Condition {
+  StoreFull,
+  StoreEmpty,
+  TargetExists(RawObjectId),
+  TicksToLiveBelow(u32),
+  RoomEnergyAbove(u32), // Energy available for spawning in the creep's room
+  Not(Box<Condition>),
+  All(Vec<Condition>),
+  Any(Vec<Condition>),
+}
+
+impl Condition {
+  // Anything about a creep that cannot be found is false
+  pub fn evaluate(&self, world: &dyn GameWorld, creep_name: &str) -> bool {
+    match self {
+      Condition::StoreFull => world.creep(creep_name).map(|creep| creep.free_capacity() == 0).unwrap_or(false),
+      Condition::StoreEmpty => world.creep(creep_name).map(|creep| creep.energy == 0).unwrap_or(false),
+      Condition::TargetExists(target_id) => world.object_pos(*target_id).is_some(),
+      Condition::TicksToLiveBelow(ticks) => world.creep(creep_name).and_then(|creep| creep.ticks_to_live).map(|ticks_to_live| ticks_to_live < *ticks).unwrap_or(false),
+      Condition::RoomEnergyAbove(energy) => world.creep(creep_name)
+        .and_then(|creep| world.room(creep.pos.room_name()))
+        .map(|room| room.energy_available > *energy)
+        .unwrap_or(false),
+      Condition::Not(condition) => !condition.evaluate(world, creep_name),
+      Condition::All(conditions) => conditions.iter().all(|condition| condition.evaluate(world, creep_name)),
+      Condition::Any(conditions) => conditions.iter().any(|condition| condition.evaluate(world, creep_name)),
+    }
+  }
 }
 
 impl Task {
-  pub fn run(&mut self, creep: Creep) -> TaskReturn {
+  fn complete_on_ok(return_code: ReturnCode) -> TaskReturn {
+    match return_code {
+      ReturnCode::Ok => TaskReturn::Complete,
+      return_code => TaskReturn::Err(TaskError::new(return_code)),
+    }
+  }
+
+  pub fn label(&self) -> &'static str {
+    match self {
+      Task::Move(_) => "Move",
+      Task::MoveTo { .. } => "MoveTo",
+      Task::Tow(_, _) => "Tow",
+      Task::Harvest(_) => "Harvest",
+      Task::Transfer { .. } => "Transfer",
+      Task::Withdraw { .. } => "Withdraw",
+      Task::Pickup(_) => "Pickup",
+      Task::Drop { .. } => "Drop",
+      Task::Build(_) => "Build",
+      Task::Repair(_) => "Repair",
+      Task::UpgradeController(_) => "UpgradeController",
+      Task::Dismantle(_) => "Dismantle",
+      Task::ClaimController(_) => "ClaimController",
+      Task::ReserveController(_) => "ReserveController",
+      Task::Heal(_) => "Heal",
+      Task::Attack(_) => "Attack",
+      Task::RangedAttack(_) => "RangedAttack",
+      Task::Continuous(_) => "Continuous",
+      Task::Perpetual(_) => "Perpetual",
+      Task::MultiStep(_) => "MultiStep",
+      Task::Until { .. } => "Until",
+      Task::While { .. } => "While",
+      Task::Repeat { .. } => "Repeat",
+      Task::IfElse { .. } => "IfElse",
+      Task::Simultaneous(_) => "Simultaneous",
+    }
+  }
+
+  // Tasks run as part of this one
+  pub fn subtasks(&self) -> Vec<&Task> {
+    match self {
+      Task::Continuous(task) | Task::Perpetual(task) => vec![task.as_ref()],
+      Task::MultiStep(task_list) => task_list.iter().map(Box::as_ref).collect(),
+      Task::Until { task, progress, .. } | Task::While { task, progress, .. } | Task::Repeat { task, progress, .. } => {
+        std::iter::once(task).chain(progress.iter()).map(Box::as_ref).collect()
+      },
+      Task::IfElse { then, otherwise, .. } => vec![then.as_ref(), otherwise.as_ref()],
+      Task::Simultaneous(task_list) => task_list.iter().map(Box::as_ref).collect(),
+      _ => vec![],
+    }
+  }
+
+  // Catches tasks that could never run, for tasks that come from outside the hive. Targets in rooms without vision
+  // cannot be told apart from missing ones and are not checked.
+  pub fn validate(&self, world: &dyn GameWorld) -> Result<(),String> {
+    match self {
+      Task::Tow(towed_name, _) if world.creep(towed_name).is_none() => return Err(format!("Tow: creep {:?} does not exist", towed_name)),
+      Task::MultiStep(task_list) if task_list.is_empty() => return Err(String::from("MultiStep has no steps")),
+      Task::Simultaneous(task_list) if task_list.is_empty() => return Err(String::from("Simultaneous has no tasks")),
+      Task::Repeat { times: 0, .. } => return Err(String::from("Repeat has to run at least once")),
+      _ => {},
+    }
+    self.subtasks().into_iter().try_for_each(|task| task.validate(world))
+  }
+
+  pub fn target(&self) -> Option<TaskTarget> {
+    match self {
+      Task::MoveTo { target, .. } => Some(TaskTarget::Position(*target)),
+      Task::Tow(towed_name, _) => Some(TaskTarget::Creep(towed_name.to_owned())),
+      Task::Transfer { target, .. } | Task::Withdraw { target, .. } => Some(TaskTarget::Object(*target)),
+      Task::Harvest(id) | Task::Pickup(id) | Task::Build(id) | Task::Repair(id) | Task::UpgradeController(id) | Task::Dismantle(id)
+        | Task::ClaimController(id) | Task::ReserveController(id) | Task::Heal(id) | Task::Attack(id) | Task::RangedAttack(id) => Some(TaskTarget::Object(*id)),
+      _ => None,
+    }
+  }
+
+  // Runs one tick of the current iteration of a loop, starting a new copy of the task when the last one finished
+  fn run_iteration(task: &Task, progress: &mut Option<Box<Task>>, world: &mut dyn GameWorld, creep_name: &str) -> TaskReturn {
+    let current = progress.get_or_insert_with(|| Box::new(task.to_owned()));
+    let task_return = current.run(world, creep_name);
+    if !matches!(task_return, TaskReturn::ProgressMade) {
+      *progress = None;
+    }
+    task_return
+  }
+
+  // Intents the task may issue for its creep on its next run
+  pub fn slots(&self) -> Vec<IntentSlot> {
+    match self {
+      Task::Move(_) | Task::MoveTo { .. } => vec![IntentSlot::Move],
+      Task::Tow(_, _) => vec![IntentSlot::Pull, IntentSlot::Move],
+      Task::Harvest(_) => vec![IntentSlot::Harvest],
+      Task::Transfer { .. } => vec![IntentSlot::Transfer],
+      Task::Withdraw { .. } => vec![IntentSlot::Withdraw],
+      Task::Pickup(_) => vec![IntentSlot::Pickup],
+      Task::Drop { .. } => vec![IntentSlot::Drop],
+      Task::Build(_) => vec![IntentSlot::Build],
+      Task::Repair(_) => vec![IntentSlot::Repair],
+      Task::UpgradeController(_) => vec![IntentSlot::UpgradeController],
+      Task::Dismantle(_) => vec![IntentSlot::Dismantle],
+      Task::ClaimController(_) => vec![IntentSlot::ClaimController],
+      Task::ReserveController(_) => vec![IntentSlot::ReserveController],
+      Task::Heal(_) => vec![IntentSlot::Heal],
+      Task::Attack(_) => vec![IntentSlot::Attack],
+      Task::RangedAttack(_) => vec![IntentSlot::RangedAttack],
+      Task::Continuous(task) | Task::Perpetual(task) => task.slots(),
+      Task::MultiStep(task_list) => task_list.front().map(|task| task.slots()).unwrap_or_default(),
+      Task::Until { task, progress, .. } | Task::While { task, progress, .. } | Task::Repeat { task, progress, .. } => progress.as_ref().unwrap_or(task).slots(),
+      Task::IfElse { then, chosen: Some(true), .. } => then.slots(),
+      Task::IfElse { otherwise, chosen: Some(false), .. } => otherwise.slots(),
+      Task::IfElse { then, otherwise, chosen: None, .. } => then.slots().into_iter().chain(otherwise.slots()).collect(),
+      Task::Simultaneous(task_list) => task_list.iter().flat_map(|task| task.slots()).collect(),
+    }
+  }
+
+  pub fn run(&mut self, world: &mut dyn GameWorld, creep_name: &str) -> TaskReturn {
+    match self.execute(world, creep_name) {
+      TaskReturn::Err(error) => TaskReturn::Err(error.within(self.label(), self.target(), world.time())),
+      task_return => task_return,
+    }
+  }
+
+  fn execute(&mut self, world: &mut dyn GameWorld, creep_name: &str) -> TaskReturn {
     match self {
 
-      Task::Move(direction) => {
-        match creep.move_direction(direction.to_owned()) {
-          ReturnCode::Ok => TaskReturn::Complete,
-          return_code => TaskReturn::Err(return_code),
+      Task::Move(direction) => Self::complete_on_ok(world.creep_move_direction(creep_name, direction.to_owned())),
+
+      Task::MoveTo { target, range, path } => {
+        let creep = match world.creep(creep_name) {
+          Some(creep) => creep,
+          None => return TaskReturn::Err(TaskError::new(ReturnCode::NotFound)),
+        };
+        if creep.pos.room_name() == target.room_name() && creep.pos.get_range_to(target) <= *range {
+          return TaskReturn::Complete;
         }
+        path.travel(world, &creep, *target, *range)
       },
 
-      Task::Tow(creep_name, direction) => {
-        if let Some(towed) = game::creeps().get(creep_name.to_owned()) {
-          match (creep.pull(&towed),towed.move_pulled_by(&creep)) {
-            (ReturnCode::Ok,ReturnCode::Ok) => {
-              match creep.move_direction(direction.to_owned()) {
-                ReturnCode::Ok => TaskReturn::Complete,
-                return_code => TaskReturn::Err(return_code),
-              }
-            },
-            (return_code,_) if return_code != ReturnCode::Ok => TaskReturn::Err(return_code),
-            (_,return_code) if return_code != ReturnCode::Ok => TaskReturn::Err(return_code),
-            return_codes => panic!("Failed to handle return codes: {:?}, {:?}", return_codes.0, return_codes.1),
+      Task::Tow(towed_name, direction) => {
+        if world.creep(towed_name).is_some() {
+          match (world.creep_pull(creep_name, towed_name),world.creep_move_pulled_by(towed_name, creep_name)) {
+            (ReturnCode::Ok,ReturnCode::Ok) => Self::complete_on_ok(world.creep_move_direction(creep_name, direction.to_owned())),
+            (pull_code,follow_code) => TaskReturn::Err(TaskError::from_codes(vec![pull_code, follow_code])),
           }
         } else {
-          TaskReturn::Err(ReturnCode::InvalidTarget)
+          TaskReturn::Err(TaskError::new(ReturnCode::InvalidTarget))
         }
       },
 
-      Task::Harvest(source_id) => {
-        let source = ObjectId::<Source>::from(source_id.to_owned()).resolve().unwrap();
-        match creep.harvest(&source) {
-          ReturnCode::Ok => TaskReturn::Complete,
-          return_code => TaskReturn::Err(return_code),
-        }
-      },
+      Task::Harvest(source_id) => Self::complete_on_ok(world.creep_harvest(creep_name, source_id.to_owned())),
+
+      // Targets are only ever held by id, an id that no longer resolves comes back from the world as InvalidTarget
+      Task::Transfer { target, resource_type, amount } => Self::complete_on_ok(world.creep_transfer(creep_name, *target, *resource_type, *amount)),
+      Task::Withdraw { target, resource_type, amount } => Self::complete_on_ok(world.creep_withdraw(creep_name, *target, *resource_type, *amount)),
+      Task::Pickup(resource_id) => Self::complete_on_ok(world.creep_pickup(creep_name, *resource_id)),
+      Task::Drop { resource_type, amount } => Self::complete_on_ok(world.creep_drop(creep_name, *resource_type, *amount)),
+      Task::Build(site_id) => Self::complete_on_ok(world.creep_build(creep_name, *site_id)),
+      Task::Repair(target_id) => Self::complete_on_ok(world.creep_repair(creep_name, *target_id)),
+      Task::UpgradeController(controller_id) => Self::complete_on_ok(world.creep_upgrade_controller(creep_name, *controller_id)),
+      Task::Dismantle(target_id) => Self::complete_on_ok(world.creep_dismantle(creep_name, *target_id)),
+      Task::ClaimController(controller_id) => Self::complete_on_ok(world.creep_claim_controller(creep_name, *controller_id)),
+      Task::ReserveController(controller_id) => Self::complete_on_ok(world.creep_reserve_controller(creep_name, *controller_id)),
+      Task::Heal(target_id) => Self::complete_on_ok(world.creep_heal(creep_name, *target_id)),
+      Task::Attack(target_id) => Self::complete_on_ok(world.creep_attack(creep_name, *target_id)),
+      Task::RangedAttack(target_id) => Self::complete_on_ok(world.creep_ranged_attack(creep_name, *target_id)),
 
       Task::Continuous(task) => {
-        match task.run(creep) {
+        match task.run(world, creep_name) {
           TaskReturn::Complete => TaskReturn::ProgressMade,
           other_return => other_return,
         }
       },
 
       Task::Perpetual(task) => {
-        task.run(creep);
+        task.run(world, creep_name);
         TaskReturn::ProgressMade
       },
 
       Task::MultiStep(task_list) => {
         match task_list.front_mut() {
           Some(task) => {
-            match task.run(creep) {
+            match task.run(world, creep_name) {
               TaskReturn::Complete => {
                 task_list.pop_front();
                 if !task_list.is_empty() {
@@ -87,53 +393,367 @@ impl Task {
         }
       },
 
+      Task::Until { condition, task, progress } => {
+        if condition.evaluate(world, creep_name) {
+          return TaskReturn::Complete;
+        }
+        match Self::run_iteration(task, progress, world, creep_name) {
+          TaskReturn::Complete => TaskReturn::ProgressMade,
+          other_return => other_return,
+        }
+      },
+
+      Task::While { condition, task, progress } => {
+        if !condition.evaluate(world, creep_name) {
+          return TaskReturn::Complete;
+        }
+        match Self::run_iteration(task, progress, world, creep_name) {
+          TaskReturn::Complete => TaskReturn::ProgressMade,
+          other_return => other_return,
+        }
+      },
+
+      Task::Repeat { times, task, completed, progress } => {
+        if *completed >= *times {
+          return TaskReturn::Complete;
+        }
+        match Self::run_iteration(task, progress, world, creep_name) {
+          TaskReturn::Complete => {
+            *completed += 1;
+            if *completed >= *times {
+              TaskReturn::Complete
+            } else {
+              TaskReturn::ProgressMade
+            }
+          },
+          other_return => other_return,
+        }
+      },
+
+      Task::IfElse { condition, then, otherwise, chosen } => {
+        let chose_then = *chosen.get_or_insert_with(|| condition.evaluate(world, creep_name));
+        if chose_then {
+          then.run(world, creep_name)
+        } else {
+          otherwise.run(world, creep_name)
+        }
+      },
+
+      // A task still waiting on a recoverable failure does not hold the others back, the failure is only reported
+      // when nothing else got anywhere this tick
+      Task::Simultaneous(task_list) => {
+        let mut used_slots: Vec<IntentSlot> = vec![];
+        let mut progress_made = false;
+        let mut first_failure = None;
+
+        let mut index = 0;
+        while index < task_list.len() {
+          let slots = task_list[index].slots();
+          if !slots.iter().all(|slot| used_slots.iter().all(|used_slot| slot.compatible_with(*used_slot))) {
+            index += 1;
+            continue;
+          }
+          used_slots.extend(slots);
+
+          match task_list[index].run(world, creep_name) {
+            TaskReturn::Complete => {
+              task_list.remove(index);
+              progress_made = true;
+              continue;
+            },
+            TaskReturn::ProgressMade => progress_made = true,
+            TaskReturn::Err(error) if error.failure() == Failure::Recoverable => {
+              first_failure.get_or_insert(error);
+            },
+            TaskReturn::Err(error) => return TaskReturn::Err(error),
+          }
+          index += 1;
+        }
+
+        match (task_list.is_empty(), first_failure) {
+          (true, _) => TaskReturn::Complete,
+          (false, Some(error)) if !progress_made => TaskReturn::Err(error),
+          _ => TaskReturn::ProgressMade,
+        }
+      },
+
+    }
+  }
+}
+
+// Whether a failed task is worth trying again next tick
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+  Recoverable,
+  Fatal,
+}
+
+impl Failure {
+  pub fn classify(return_code: ReturnCode) -> Self {
+    match return_code {
+      ReturnCode::Tired | ReturnCode::Busy | ReturnCode::NotEnough | ReturnCode::Full | ReturnCode::NotInRange | ReturnCode::NoPath => Failure::Recoverable,
+      _ => Failure::Fatal,
+    }
+  }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Deadline {
+  Tick(u32), // Abandoned once this game tick has passed
+  Ticks(u32), // Abandoned this many ticks after the task first ran
+}
+
+impl Deadline {
+  pub fn passed(&self, time: u32, started: u32) -> bool {
+    match self {
+      Deadline::Tick(tick) => time > *tick,
+      Deadline::Ticks(ticks) => time >= started.saturating_add(*ticks),
+    }
+  }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum TaskStatus {
+  Running,
+  Completed,
+  Failed(TaskError),
+  CreepDied,
+  TimedOut,
+}
+
+// The subsystem that handed out a task and hears how it ended
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TaskOwner {
+  MineRoom(RoomName),
+  SpawnRoom(RoomName),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TaskEvent {
+  pub owner: TaskOwner,
+  pub creep_name: String, // The leader for group tasks
+  pub status: TaskStatus, // Never Running
+  pub time: u32,
+}
+
+// A task only runs while nothing of a higher priority is waiting for the same creep
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TaskPriority {
+  Low,
+  Normal,
+  High,
+  Urgent, // Fleeing, refilling an empty spawn, emergency repairs
+}
+
+impl Default for TaskPriority {
+  fn default() -> Self {
+    TaskPriority::Normal
+  }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ScheduledTask {
+  pub task: Task,
+  pub priority: TaskPriority,
+  pub owner: Option<TaskOwner>,
+  pub deadline: Option<Deadline>,
+  pub started: Option<u32>, // Tick the task first ran
+  pub max_retries: u32,
+  pub retries: u32, // Recoverable failures in a row
+}
+
+impl ScheduledTask {
+  pub fn new(task: Task) -> Self {
+    Self {
+      task,
+      priority: TaskPriority::default(),
+      owner: None,
+      deadline: None,
+      started: None,
+      max_retries: TASK_RETRIES,
+      retries: 0,
+    }
+  }
+
+  pub fn with_priority(mut self, priority: TaskPriority) -> Self {
+    self.priority = priority;
+    self
+  }
+
+  pub fn with_owner(mut self, owner: TaskOwner) -> Self {
+    self.owner = Some(owner);
+    self
+  }
+
+  pub fn with_deadline(mut self, deadline: Deadline) -> Self {
+    self.deadline = Some(deadline);
+    self
+  }
+
+  pub fn with_retries(mut self, max_retries: u32) -> Self {
+    self.max_retries = max_retries;
+    self
+  }
+
+  pub fn run(&mut self, world: &mut dyn GameWorld, creep_name: &str) -> TaskStatus {
+    let time = world.time();
+    let started = *self.started.get_or_insert(time);
+    if self.deadline.map_or(false, |deadline| deadline.passed(time, started)) {
+      return TaskStatus::TimedOut;
+    }
+    if world.creep(creep_name).is_none() {
+      return TaskStatus::CreepDied;
+    }
+
+    match self.task.run(world, creep_name) {
+      TaskReturn::ProgressMade => {
+        self.retries = 0;
+        TaskStatus::Running
+      },
+      TaskReturn::Complete => TaskStatus::Completed,
+      TaskReturn::Err(error) if error.failure() == Failure::Recoverable && self.retries < self.max_retries => {
+        self.retries += 1;
+        TaskStatus::Running
+      },
+      TaskReturn::Err(error) => TaskStatus::Failed(error),
     }
   }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Tasks {
-  pub task_list: HashMap<String,Task>, // String == Valid creep name
+  // String == Valid creep name. Each creep's tasks are ordered by priority and only the last one runs, the others
+  // keep their progress until it is their turn again.
+  pub task_list: HashMap<String,Vec<ScheduledTask>>,
+  pub groups: HashMap<String,GroupTask>, // String == Leader's creep name
+  pub events: Vec<TaskEvent>, // How owned tasks ended this tick, handed to their owners at the start of the next
 }
 
 impl Tasks {
   pub fn new() -> Self {
     Self {
       task_list: HashMap::new(),
+      groups: HashMap::new(),
+      events: vec![],
     }
   }
 
-  pub fn run(&mut self) {
-    let mut completed_creep_names: Vec<String> = vec![];
+  pub fn run(&mut self, world: &mut dyn GameWorld) {
+    let time = world.time();
+    let mut ended: Vec<(String,Option<TaskOwner>,TaskStatus)> = vec![];
 
-    for (creep_name, task) in self.task_list.iter_mut() {
-      match Self::run_task(creep_name, task) {
-        TaskReturn::ProgressMade => {},
-        _ => {
-          completed_creep_names.push(creep_name.to_owned());
+    // Sorted so that a replay of the tick issues its intents in the same order
+    let mut creep_names = self.task_list.keys().cloned().collect::<Vec<_>>();
+    creep_names.sort();
+    for creep_name in creep_names {
+      let task_stack = match self.task_list.get_mut(&creep_name) {
+        Some(task_stack) => task_stack,
+        None => continue,
+      };
+      if world.creep(&creep_name).is_none() {
+        ended.extend(task_stack.drain(..).rev().map(|scheduled_task| (creep_name.to_owned(), scheduled_task.owner, TaskStatus::CreepDied)));
+        continue;
+      }
+
+      let status = match task_stack.last_mut() {
+        Some(scheduled_task) => scheduled_task.run(world, &creep_name),
+        None => continue,
+      };
+      if status != TaskStatus::Running {
+        if let Some(scheduled_task) = task_stack.pop() {
+          ended.push((creep_name, scheduled_task.owner, status));
+        }
+      }
+    }
+    self.task_list.retain(|_, task_stack| !task_stack.is_empty());
+
+    let mut leader_names = self.groups.keys().cloned().collect::<Vec<_>>();
+    leader_names.sort();
+    for leader_name in leader_names {
+      let status = match self.groups.get_mut(&leader_name) {
+        Some(group_task) => group_task.run(world, &leader_name),
+        None => continue,
+      };
+      if status != TaskStatus::Running {
+        if let Some(group_task) = self.groups.remove(&leader_name) {
+          ended.push((leader_name, group_task.owner, status));
         }
       }
     }
 
-    for creep_name in completed_creep_names {
-      self.task_list.remove(&creep_name);
+    for (creep_name, owner, status) in ended {
+      if let TaskStatus::Failed(error) = &status {
+        console::warn(format!("[ tasks ] Task for {:?} abandoned: {}", creep_name, error));
+      }
+      if let Some(owner) = owner {
+        self.events.push(TaskEvent { owner, creep_name, status, time });
+      }
     }
   }
 
-  pub fn run_task(creep_name: &str, task: &mut Task) -> TaskReturn {
-    if let Some(creep) = game::creeps().get(creep_name.to_owned()) {
-      task.run(creep)
+  pub fn take_events(&mut self) -> Vec<TaskEvent> {
+    std::mem::take(&mut self.events)
+  }
+
+  pub fn run_task(world: &mut dyn GameWorld, creep_name: &str, task: &mut Task) -> TaskReturn {
+    if world.creep(creep_name).is_some() {
+      task.run(world, creep_name)
     } else {
-      TaskReturn::Err(ReturnCode::NotFound)
+      TaskReturn::Err(TaskError::new(ReturnCode::NotFound).within(task.label(), task.target(), world.time()))
     }
   }
 
-  pub fn add_task(&mut self, creep_name: String, task: Task) {
-    self.task_list.insert(creep_name, task);
+  pub fn add_task(&mut self, creep_name: String, task: Task) -> Result<(),String> {
+    self.add_scheduled_task(creep_name, ScheduledTask::new(task))
+  }
+
+  // A task with a higher priority than the running one takes over straight away, otherwise it waits behind every task
+  // with the same or a higher priority. Creeps in a group only take orders from the group until it is released.
+  pub fn add_scheduled_task(&mut self, creep_name: String, scheduled_task: ScheduledTask) -> Result<(),String> {
+    if let Some(leader_name) = self.group_of(&creep_name) {
+      return Err(format!("Creep {:?} is in the group led by {:?}, cancel the group first", creep_name, leader_name));
+    }
+    let task_stack = self.task_list.entry(creep_name).or_default();
+    let index = task_stack.iter()
+      .position(|queued_task| queued_task.priority >= scheduled_task.priority)
+      .unwrap_or(task_stack.len());
+    task_stack.insert(index, scheduled_task);
+    Ok(())
+  }
+
+  // The task that runs next for the creep
+  pub fn current_task(&self, creep_name: &str) -> Option<&ScheduledTask> {
+    self.task_list.get(creep_name).and_then(|task_stack| task_stack.last())
+  }
+
+  // Fails without changing anything when a member is already busy
+  pub fn add_group(&mut self, leader_name: String, group_task: GroupTask) -> Result<(),String> {
+    if let Some(busy_name) = group_task.members(&leader_name).find(|creep_name| self.has_task(creep_name)) {
+      return Err(format!("Creep {:?} is busy", busy_name));
+    }
+    self.groups.insert(leader_name, group_task);
+    Ok(())
+  }
+
+  // Leader of the group the creep belongs to
+  pub fn group_of(&self, creep_name: &str) -> Option<&str> {
+    self.groups.iter()
+      .find(|(leader_name, group_task)| group_task.members(leader_name).any(|member_name| member_name == creep_name))
+      .map(|(leader_name, _)| leader_name.as_str())
+  }
+
+  // Drops every task the creep has and any group it is in, returns how many were dropped
+  pub fn cancel(&mut self, creep_name: &str) -> usize {
+    let mut cancelled = self.task_list.remove(creep_name).map(|task_stack| task_stack.len()).unwrap_or(0);
+    if let Some(leader_name) = self.group_of(creep_name).map(str::to_owned) {
+      self.groups.remove(&leader_name);
+      cancelled += 1;
+    }
+    cancelled
   }
 
   pub fn has_task(&self, creep_name: &str) -> bool {
-    self.task_list.contains_key(creep_name)
+    self.current_task(creep_name).is_some() || self.group_of(creep_name).is_some()
   }
 }
 
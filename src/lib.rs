@@ -1,29 +1,112 @@
 extern crate console_error_panic_hook;
 
-// use std::sync::Mutex;
+#[cfg(target_arch = "wasm32")]
+use std::sync::Mutex;
+#[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
+pub mod commands;
 pub mod console;
 pub mod constants;
 pub mod hive;
 pub mod memory;
+pub mod recorder;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod scenario;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod sim;
+pub mod world;
 
+#[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
 pub fn setup() {
   console_error_panic_hook::set_once();
 }
 
+#[cfg(target_arch = "wasm32")]
+static MEM: Mutex<memory::cache::MemoryCache> = Mutex::new(memory::cache::MemoryCache::new());
+
+#[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
 pub fn game_loop() {
-  // static MEM: Mutex<Option<memory::Memory>> = Mutex::new(None);
-  // let mut mem_ref = MEM.lock().unwrap();
+  let mut world = world::ScreepsWorld::new();
+
+  let mut cache = MEM.lock().unwrap();
+  let mem = match cache.get(&mut world) {
+    Some(mem) => mem,
+    None => return,
+  };
+  let record = mem.recorder.capture(&world, mem);
+
+  let mut intents = world::IntentQueue::new(&mut world, mem.settings.dry_run, mem.settings.log_intents);
+  mem.run(&mut intents);
+  let results = intents.dispatch();
+  mem.storage.activate(&mut world);
+  mem.note_dispatched(&results);
 
-  // let mut mem = mem_ref.to_owned().unwrap_or_else(|| memory::Memory::new());
-  let mut mem = memory::load();
+  if let Some(record) = record {
+    mem.recorder.store(&mut world, record, &results);
+    // The ring's index has to survive a global reset or the next recording overwrites the wrong segments
+    mem.mark_dirty();
+  }
 
-  mem.run();
+  if mem.settings.dry_run {
+    cache.invalidate();
+  } else {
+    cache.persist(&mut world);
+  }
+}
+
+// Console commands change memory between ticks, so anything they change is saved straight away
+#[cfg(target_arch = "wasm32")]
+fn run_command<F>(changes_memory: bool, command: F) -> String
+where F: FnOnce(&mut memory::Memory, &dyn world::GameWorld) -> Result<String,String> {
+  let mut world = world::ScreepsWorld::new();
 
-  memory::save(&mem);
+  let mut cache = MEM.lock().unwrap();
+  let result = match cache.get(&mut world) {
+    Some(mem) => {
+      let result = command(mem, &world);
+      if changes_memory && result.is_ok() {
+        mem.mark_dirty();
+      }
+      result
+    },
+    None => Err(String::from("Memory is not loaded yet, try again next tick")),
+  };
+  if changes_memory {
+    cache.persist(&mut world);
+  }
 
-  // *mem_ref = Some(mem);
-}
\ No newline at end of file
+  result.unwrap_or_else(|e| format!("Error: {}", e))
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn add_task(creep_name: String, task_json: String, priority: Option<String>) -> String {
+  run_command(true, |mem, world| commands::add_task(&mut mem.hive.tasks, world, &creep_name, &task_json, priority.as_deref()))
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn list_tasks(creep_name: Option<String>) -> String {
+  run_command(false, |mem, _| Ok(commands::list_tasks(&mem.hive.tasks, creep_name.as_deref())))
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn cancel_tasks(creep_name: String) -> String {
+  run_command(true, |mem, _| commands::cancel_tasks(&mut mem.hive.tasks, &creep_name))
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn list_spawn_queue() -> String {
+  run_command(false, |mem, _| Ok(commands::list_spawn_queue(&mem.hive)))
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn set_mining_mode(room_name: String, source_id: String, mining_mode_json: String) -> String {
+  run_command(true, |mem, _| commands::set_mining_mode(&mut mem.hive, &room_name, &source_id, &mining_mode_json))
+}
@@ -0,0 +1,101 @@
+use crate::constants::{BACKUP_SEGMENT_START, BACKUP_SLOTS, BACKUP_SLOT_SEGMENTS, SEGMENT_SIZE_LIMIT};
+use crate::world::GameWorld;
+use super::storage::split_chunks;
+
+// Each backup slot starts with this header: `#bk1:<tick>:<length>:<checksum>:` followed by the serialized memory,
+// continued across as many of the slot's segments as it needs
+pub const BACKUP_HEADER: &str = "#bk1:";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backup {
+  pub time: u32,
+  pub serialized: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stored {
+  Written,
+  Deferred, // Not enough segment writes left this tick
+  TooLarge { segments: usize }, // Segments the backup would need, more than a slot has
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Restore {
+  Found(Backup),
+  Pending, // Backup segments have been requested and will be readable next tick
+  Unavailable,
+}
+
+pub fn slot_segments(slot: u8) -> Vec<u8> {
+  let start = BACKUP_SEGMENT_START + slot * BACKUP_SLOT_SEGMENTS;
+  (start..(start + BACKUP_SLOT_SEGMENTS)).collect()
+}
+
+pub fn all_segments() -> Vec<u8> {
+  (0..BACKUP_SLOTS).flat_map(slot_segments).collect()
+}
+
+// FNV-1a, only needs to catch truncation and stray edits
+pub fn checksum(serialized: &str) -> u32 {
+  serialized.bytes().fold(0x811c9dc5, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x01000193))
+}
+
+// Only writes the segments the backup needs, and nothing at all when they cannot all be written this tick
+pub fn store(world: &mut dyn GameWorld, slot: u8, serialized: &str) -> Stored {
+  let contents = format!("{}{}:{}:{:08x}:{}", BACKUP_HEADER, world.time(), serialized.chars().count(), checksum(serialized), serialized);
+  let segments = slot_segments(slot);
+
+  let chunks = split_chunks(&contents, SEGMENT_SIZE_LIMIT);
+  if chunks.len() > segments.len() {
+    return Stored::TooLarge { segments: chunks.len() };
+  }
+  if chunks.len() > world.segment_writes_left() {
+    return Stored::Deferred;
+  }
+
+  for (segment_id, chunk) in segments.iter().zip(chunks.iter()) {
+    world.set_segment(*segment_id, chunk);
+  }
+  Stored::Written
+}
+
+// Reads one slot, None when it is empty, incomplete or fails its checksum
+pub fn read(world: &dyn GameWorld, slot: u8) -> Option<Backup> {
+  let mut segments = slot_segments(slot).into_iter();
+  let first = world.segment(segments.next()?)?;
+
+  let mut fields = first.strip_prefix(BACKUP_HEADER)?.splitn(4, ':');
+  let time = fields.next()?.parse::<u32>().ok()?;
+  let length = fields.next()?.parse::<usize>().ok()?;
+  let expected_checksum = u32::from_str_radix(fields.next()?, 16).ok()?;
+  let mut serialized = fields.next()?.to_owned();
+  // Segments past the end are left over from a larger backup
+  for segment_id in segments {
+    if serialized.chars().count() >= length {
+      break;
+    }
+    serialized.push_str(&world.segment(segment_id)?);
+  }
+
+  if serialized.chars().count() != length || checksum(&serialized) != expected_checksum {
+    return None;
+  }
+  Some(Backup {
+    time,
+    serialized,
+  })
+}
+
+// Newest backup that passes its checksum. The first call requests every backup segment if they are not active yet.
+pub fn restore(world: &mut dyn GameWorld) -> Restore {
+  let segments = all_segments();
+  if segments.iter().any(|segment_id| world.segment(*segment_id).is_none()) {
+    world.set_active_segments(&segments);
+    return Restore::Pending;
+  }
+
+  match (0..BACKUP_SLOTS).filter_map(|slot| read(world, slot)).max_by_key(|backup| backup.time) {
+    Some(backup) => Restore::Found(backup),
+    None => Restore::Unavailable,
+  }
+}
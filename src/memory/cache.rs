@@ -0,0 +1,129 @@
+use crate::console;
+use crate::constants::{BACKUP_INTERVAL, BACKUP_SLOTS, BACKUP_SLOT_SEGMENTS};
+use crate::world::GameWorld;
+use super::{Memory, backup::{self, Restore, Stored}};
+
+// Keeps the deserialized Memory on the heap between ticks. RawMemory is only parsed again after a global reset or when
+// something other than `persist` has written to it, and only written on the save interval or when memory is dirty.
+pub struct MemoryCache {
+  memory: Option<Memory>,
+  last_marker: (usize,u32), // Length and checksum of RawMemory as we last read or wrote it
+  last_saved: u32,
+  last_backup: Option<u32>,
+  next_backup_slot: u8,
+  restore_requested: Option<u32>, // Tick the backup segments were requested on
+}
+
+impl MemoryCache {
+  pub const fn new() -> Self {
+    Self {
+      memory: None,
+      last_marker: (0, 0),
+      last_saved: 0,
+      last_backup: None,
+      next_backup_slot: 0,
+      restore_requested: None,
+    }
+  }
+
+  // Saves keeping a second copy of RawMemory around just to notice it was edited
+  fn marker(serialized: &str) -> (usize,u32) {
+    (serialized.len(), backup::checksum(serialized))
+  }
+
+  // None while waiting a tick for backup segments to restore from, the hive should not run until memory is back
+  pub fn get(&mut self, world: &mut dyn GameWorld) -> Option<&mut Memory> {
+    let marker = Self::marker(&world.raw_memory());
+    if self.memory.is_some() && marker != self.last_marker {
+      console::log(String::from("[ memory ] RawMemory was edited externally, reloading"));
+      self.memory = None;
+    }
+
+    if self.memory.is_none() {
+      let memory = match super::load(world) {
+        Ok(memory) => memory,
+        Err(e) => self.recover(world, e)?,
+      };
+      self.memory = Some(memory);
+      self.last_marker = marker;
+      self.last_saved = world.time();
+      self.restore_requested = None;
+    }
+    self.memory.as_mut()
+  }
+
+  // Falls back to the newest backup that passes its checksum and only starts over when there is none
+  fn recover(&mut self, world: &mut dyn GameWorld, error: String) -> Option<Memory> {
+    let time = world.time();
+    let restore = match backup::restore(world) {
+      Restore::Pending if self.restore_requested.map_or(true, |requested| requested == time) => {
+        console::warn(format!("[ memory ] Unable to load memory, waiting for backup segments: {}", error));
+        self.restore_requested = Some(time);
+        return None;
+      },
+      Restore::Pending => Restore::Unavailable,
+      restore => restore,
+    };
+
+    let (mut memory, message) = match restore {
+      Restore::Found(backup) => match super::decode(&backup.serialized) {
+        Ok(memory) => (memory, format!("Memory was unreadable ({}), restored the backup from tick {}", error, backup.time)),
+        Err(backup_error) => (Memory::new(world), format!("Memory was unreadable ({}) and so was the backup from tick {} ({}), starting over", error, backup.time, backup_error)),
+      },
+      _ => (Memory::new(world), format!("Memory was unreadable ({}) and there is no backup, starting over", error)),
+    };
+    console::warn(format!("[ memory ] {}", message));
+    world.notify(&message);
+
+    memory.mark_dirty();
+    Some(memory)
+  }
+
+  // Drops the cached memory so the next tick starts again from RawMemory
+  pub fn invalidate(&mut self) {
+    self.memory = None;
+  }
+
+  pub fn persist(&mut self, world: &mut dyn GameWorld) {
+    let memory = match self.memory.as_mut() {
+      Some(memory) => memory,
+      None => return,
+    };
+
+    let time = world.time();
+    if !memory.dirty && time.saturating_sub(self.last_saved) < memory.settings.save_interval {
+      return;
+    }
+
+    if let Some(serialized) = super::save(world, memory) {
+      memory.dirty = false;
+      self.last_saved = time;
+
+      // Only memory that was just serialized from a live Memory is known to be good
+      if self.last_backup.map_or(true, |last_backup| time.saturating_sub(last_backup) >= BACKUP_INTERVAL) {
+        match backup::store(world, self.next_backup_slot, &serialized) {
+          Stored::Written => {
+            self.last_backup = Some(time);
+            self.next_backup_slot = (self.next_backup_slot + 1) % BACKUP_SLOTS;
+          },
+          Stored::Deferred => {}, // Tried again on the next save
+          Stored::TooLarge { segments } => {
+            // Waits out a full interval so the player is not notified on every save
+            self.last_backup = Some(time);
+            let message = format!("Memory needs {} segments but a backup slot has {}, it is no longer being backed up", segments, BACKUP_SLOT_SEGMENTS);
+            console::warn(format!("[ memory ] {}", message));
+            world.notify(&message);
+          },
+        }
+      }
+
+      self.last_marker = Self::marker(&serialized);
+    }
+  }
+}
+
+impl Default for MemoryCache {
+  fn default() -> Self {
+    Self::new()
+  }
+}
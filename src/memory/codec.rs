@@ -0,0 +1,104 @@
+use serde::{Serialize, de::DeserializeOwned};
+
+// Binary memory starts with this header followed by the byte length and the packed bytes. JSON memory always starts
+// with `{` so the two can never be confused.
+pub const BINARY_HEADER: &str = "#mp1:";
+
+// Packed characters start here so every 15 bit value maps to a printable BMP character and never to a surrogate
+const CHAR_OFFSET: u32 = 0x100;
+const BITS_PER_CHAR: u32 = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+  Json,
+  Binary, // MessagePack with named fields, packed 15 bits to a character
+}
+
+#[cfg(feature = "binary_memory")]
+pub const SAVE_CODEC: Codec = Codec::Binary;
+#[cfg(not(feature = "binary_memory"))]
+pub const SAVE_CODEC: Codec = Codec::Json;
+
+impl Codec {
+  pub fn detect(serialized: &str) -> Codec {
+    if serialized.starts_with(BINARY_HEADER) {
+      Codec::Binary
+    } else {
+      Codec::Json
+    }
+  }
+}
+
+pub fn encode<T: Serialize>(value: &T, codec: Codec) -> Result<String,String> {
+  match codec {
+    Codec::Json => serde_json::to_string(value).map_err(|e| format!("Unable to encode JSON: {}", e)),
+    Codec::Binary => {
+      // Human readable so ids and positions take the same shape as in JSON and migrations can treat both alike
+      let mut bytes = vec![];
+      let mut serializer = rmp_serde::Serializer::new(&mut bytes).with_struct_map().with_human_readable();
+      value.serialize(&mut serializer).map_err(|e| format!("Unable to encode MessagePack: {}", e))?;
+      Ok(format!("{}{}:{}", BINARY_HEADER, bytes.len(), pack(&bytes)))
+    },
+  }
+}
+
+pub fn decode<T: DeserializeOwned>(serialized: &str) -> Result<T,String> {
+  match Codec::detect(serialized) {
+    Codec::Json => serde_json::from_str(serialized).map_err(|e| format!("Memory is not valid JSON: {}", e)),
+    Codec::Binary => {
+      let (length, packed) = serialized[BINARY_HEADER.len()..].split_once(':')
+        .ok_or_else(|| String::from("Binary memory has no length"))?;
+      let length = length.parse::<usize>().map_err(|e| format!("Binary memory has an invalid length: {}", e))?;
+      let bytes = unpack(packed, length)?;
+      let mut deserializer = rmp_serde::Deserializer::from_read_ref(&bytes).with_human_readable();
+      T::deserialize(&mut deserializer).map_err(|e| format!("Memory is not valid MessagePack: {}", e))
+    },
+  }
+}
+
+pub fn pack(bytes: &[u8]) -> String {
+  let mut packed = String::with_capacity(bytes.len() * 8 / BITS_PER_CHAR as usize + 1);
+  let mut buffer: u32 = 0;
+  let mut bits = 0;
+  for byte in bytes {
+    buffer = (buffer << 8) | *byte as u32;
+    bits += 8;
+    while bits >= BITS_PER_CHAR {
+      bits -= BITS_PER_CHAR;
+      packed.push(to_char(buffer >> bits));
+    }
+    buffer &= (1 << bits) - 1;
+  }
+  if bits > 0 {
+    packed.push(to_char(buffer << (BITS_PER_CHAR - bits)));
+  }
+  packed
+}
+
+pub fn unpack(packed: &str, length: usize) -> Result<Vec<u8>,String> {
+  let mut bytes = Vec::with_capacity(length + 1);
+  let mut buffer: u32 = 0;
+  let mut bits = 0;
+  for character in packed.chars() {
+    let value = (character as u32).checked_sub(CHAR_OFFSET)
+      .filter(|value| *value < 1 << BITS_PER_CHAR)
+      .ok_or_else(|| format!("Unexpected character {:?} in binary memory", character))?;
+    buffer = (buffer << BITS_PER_CHAR) | value;
+    bits += BITS_PER_CHAR;
+    while bits >= 8 {
+      bits -= 8;
+      bytes.push((buffer >> bits) as u8);
+    }
+    buffer &= (1 << bits) - 1;
+  }
+
+  if bytes.len() < length {
+    return Err(format!("Binary memory is truncated, expected {} bytes but found {}", length, bytes.len()));
+  }
+  bytes.truncate(length);
+  Ok(bytes)
+}
+
+fn to_char(value: u32) -> char {
+  char::from_u32(CHAR_OFFSET + (value & ((1 << BITS_PER_CHAR) - 1))).unwrap()
+}
@@ -0,0 +1,187 @@
+use serde_json::{json, Value};
+
+use super::{Memory, MemVersion, MEM_VERSION};
+use crate::constants::{MEMORY_SAVE_INTERVAL, TASK_RETRIES};
+
+// Transforms serialized memory from one version to the next. Migrations work on plain JSON values, whichever codec
+// the memory was saved with, so they never depend on the current shape of the Rust types.
+pub struct Migration {
+  pub from: MemVersion,
+  pub to: MemVersion,
+  pub migrate: fn(Value) -> Result<Value,String>,
+}
+
+// Must stay ordered, each entry picks up where the previous one left off
+pub const MIGRATIONS: &[Migration] = &[
+  Migration { from: (0,0,0,0), to: (0,1,0,0), migrate: add_settings_and_recorder },
+  Migration { from: (0,1,0,0), to: (0,2,0,0), migrate: add_save_interval },
+  Migration { from: (0,2,0,0), to: (0,3,0,0), migrate: add_storage },
+  Migration { from: (0,3,0,0), to: (0,4,0,0), migrate: schedule_tasks },
+  Migration { from: (0,4,0,0), to: (0,5,0,0), migrate: add_task_groups },
+  Migration { from: (0,5,0,0), to: (0,6,0,0), migrate: add_task_owners },
+  Migration { from: (0,6,0,0), to: (0,7,0,0), migrate: stack_tasks },
+  Migration { from: (0,7,0,0), to: (0,8,0,0), migrate: structure_task_errors },
+  Migration { from: (0,8,0,0), to: (0,9,0,0), migrate: add_spawn_queues },
+  Migration { from: (0,9,0,0), to: (0,10,0,0), migrate: add_round_trips },
+  Migration { from: (0,10,0,0), to: (0,11,0,0), migrate: add_mining_modes },
+  Migration { from: (0,11,0,0), to: (0,12,0,0), migrate: add_spawn_dispatch },
+];
+
+pub fn version_of(memory: &Value) -> Result<MemVersion,String> {
+  serde_json::from_value(memory.get("version").cloned().unwrap_or(Value::Null))
+    .map_err(|e| format!("Memory has no readable version: {}", e))
+}
+
+pub fn migrate(mut memory: Value) -> Result<Value,String> {
+  loop {
+    let version = version_of(&memory)?;
+    if version == MEM_VERSION {
+      return Ok(memory);
+    }
+
+    let migration = MIGRATIONS.iter()
+      .find(|migration| migration.from == version)
+      .ok_or_else(|| format!("No migration from version {:?} to {:?}", version, MEM_VERSION))?;
+    memory = (migration.migrate)(memory).map_err(|e| format!("Migration {:?} -> {:?} failed: {}", migration.from, migration.to, e))?;
+    memory["version"] = json!(migration.to);
+  }
+}
+
+pub fn migrate_str(serialized: &str) -> Result<Memory,String> {
+  let memory = super::codec::decode::<Value>(serialized)?;
+  serde_json::from_value(migrate(memory)?).map_err(|e| format!("Migrated memory does not deserialize: {}", e))
+}
+
+fn object_mut(memory: &mut Value) -> Result<&mut serde_json::Map<String,Value>,String> {
+  memory.as_object_mut().ok_or_else(|| String::from("Memory is not an object"))
+}
+
+// 0.0.0.0 -> 0.1.0.0 : settings and recorder added to the root
+fn add_settings_and_recorder(mut memory: Value) -> Result<Value,String> {
+  let root = object_mut(&mut memory)?;
+  root.entry("settings").or_insert_with(|| json!({ "dry_run": false, "log_intents": false }));
+  root.entry("recorder").or_insert_with(|| json!({ "remaining_ticks": 0, "recorded": [] }));
+  Ok(memory)
+}
+
+// 0.1.0.0 -> 0.2.0.0 : settings.save_interval added for the heap cache
+fn add_save_interval(mut memory: Value) -> Result<Value,String> {
+  let settings = object_mut(&mut memory)?
+    .entry("settings")
+    .or_insert_with(|| json!({ "dry_run": false, "log_intents": false }));
+  object_mut(settings)?.entry("save_interval").or_insert_with(|| json!(MEMORY_SAVE_INTERVAL));
+  Ok(memory)
+}
+
+// 0.2.0.0 -> 0.3.0.0 : segment storage index added to the root
+fn add_storage(mut memory: Value) -> Result<Value,String> {
+  object_mut(&mut memory)?.entry("storage").or_insert_with(|| json!({ "shards": {} }));
+  Ok(memory)
+}
+
+// 0.3.0.0 -> 0.4.0.0 : each task wrapped with its deadline and retry state
+fn schedule_tasks(mut memory: Value) -> Result<Value,String> {
+  let task_list = memory.pointer_mut("/hive/tasks/task_list")
+    .and_then(|task_list| task_list.as_object_mut())
+    .ok_or_else(|| String::from("Memory has no task list"))?;
+  for task in task_list.values_mut() {
+    *task = json!({ "task": task.take(), "deadline": null, "started": null, "max_retries": TASK_RETRIES, "retries": 0 });
+  }
+  Ok(memory)
+}
+
+// 0.4.0.0 -> 0.5.0.0 : group tasks added next to the task list
+fn add_task_groups(mut memory: Value) -> Result<Value,String> {
+  let tasks = memory.pointer_mut("/hive/tasks")
+    .ok_or_else(|| String::from("Memory has no tasks"))?;
+  object_mut(tasks)?.entry("groups").or_insert_with(|| json!({}));
+  Ok(memory)
+}
+
+// 0.5.0.0 -> 0.6.0.0 : tasks and groups gain an owner, finished tasks are reported through an event list
+fn add_task_owners(mut memory: Value) -> Result<Value,String> {
+  let tasks = object_mut(memory.pointer_mut("/hive/tasks").ok_or_else(|| String::from("Memory has no tasks"))?)?;
+  for list_name in ["task_list", "groups"] {
+    if let Some(list) = tasks.get_mut(list_name).and_then(|list| list.as_object_mut()) {
+      for task in list.values_mut() {
+        object_mut(task)?.entry("owner").or_insert(Value::Null);
+      }
+    }
+  }
+  tasks.entry("events").or_insert_with(|| json!([]));
+  Ok(memory)
+}
+
+// 0.6.0.0 -> 0.7.0.0 : each creep's task becomes the only entry of its task stack, at normal priority
+fn stack_tasks(mut memory: Value) -> Result<Value,String> {
+  let task_list = memory.pointer_mut("/hive/tasks/task_list")
+    .and_then(|task_list| task_list.as_object_mut())
+    .ok_or_else(|| String::from("Memory has no task list"))?;
+  for task in task_list.values_mut() {
+    object_mut(task)?.entry("priority").or_insert_with(|| json!("Normal"));
+    *task = json!([task.take()]);
+  }
+  Ok(memory)
+}
+
+// 0.7.0.0 -> 0.8.0.0 : failed task events carry a TaskError instead of a bare return code
+fn structure_task_errors(mut memory: Value) -> Result<Value,String> {
+  let events = memory.pointer_mut("/hive/tasks/events")
+    .and_then(|events| events.as_array_mut())
+    .ok_or_else(|| String::from("Memory has no task events"))?;
+  for event in events.iter_mut() {
+    let time = event["time"].clone();
+    if let Some(status) = event.get_mut("status").and_then(|status| status.get_mut("Failed")) {
+      *status = json!({ "path": [], "target": null, "return_codes": [status.take()], "time": time });
+    }
+  }
+  Ok(memory)
+}
+
+// 0.8.0.0 -> 0.9.0.0 : every spawn room gains an empty spawn queue
+fn add_spawn_queues(mut memory: Value) -> Result<Value,String> {
+  let spawn_rooms = memory.pointer_mut("/hive/spawn_rooms")
+    .and_then(|spawn_rooms| spawn_rooms.as_object_mut())
+    .ok_or_else(|| String::from("Memory has no spawn rooms"))?;
+  for spawn_room in spawn_rooms.values_mut() {
+    object_mut(spawn_room)?.entry("spawn_queue").or_insert_with(|| json!([]));
+  }
+  Ok(memory)
+}
+
+// 0.9.0.0 -> 0.10.0.0 : mine rooms remember how long each source's hauling round trip is
+fn add_round_trips(mut memory: Value) -> Result<Value,String> {
+  let mine_rooms = memory.pointer_mut("/hive/mine_rooms")
+    .and_then(|mine_rooms| mine_rooms.as_object_mut())
+    .ok_or_else(|| String::from("Memory has no mine rooms"))?;
+  for mine_room in mine_rooms.values_mut() {
+    object_mut(mine_room)?.entry("round_trips").or_insert_with(|| json!({}));
+  }
+  Ok(memory)
+}
+
+// 0.10.0.0 -> 0.11.0.0 : mine rooms gain a mining mode per source, every existing source stays drop mined
+fn add_mining_modes(mut memory: Value) -> Result<Value,String> {
+  let mine_rooms = memory.pointer_mut("/hive/mine_rooms")
+    .and_then(|mine_rooms| mine_rooms.as_object_mut())
+    .ok_or_else(|| String::from("Memory has no mine rooms"))?;
+  for mine_room in mine_rooms.values_mut() {
+    object_mut(mine_room)?.entry("mining_modes").or_insert_with(|| json!({}));
+  }
+  Ok(memory)
+}
+
+// 0.11.0.0 -> 0.12.0.0 : spawn requests remember the tick they were ordered on, queued requests have not been yet
+fn add_spawn_dispatch(mut memory: Value) -> Result<Value,String> {
+  let spawn_rooms = memory.pointer_mut("/hive/spawn_rooms")
+    .and_then(|spawn_rooms| spawn_rooms.as_object_mut())
+    .ok_or_else(|| String::from("Memory has no spawn rooms"))?;
+  for spawn_room in spawn_rooms.values_mut() {
+    if let Some(spawn_queue) = spawn_room.get_mut("spawn_queue").and_then(|spawn_queue| spawn_queue.as_array_mut()) {
+      for request in spawn_queue.iter_mut() {
+        object_mut(request)?.entry("dispatched").or_insert(Value::Null);
+      }
+    }
+  }
+  Ok(memory)
+}
@@ -0,0 +1,115 @@
+use serde::{Serialize, Deserialize};
+
+use crate::console;
+use crate::constants::MEMORY_SAVE_INTERVAL;
+use crate::world::{GameWorld, Intent, IntentResult};
+
+pub mod backup;
+pub mod cache;
+pub mod codec;
+pub mod migrations;
+pub mod storage;
+
+pub type MemVersion = (u8,u8,u8,u8);
+
+pub const MEM_VERSION: MemVersion = (0,12,0,0);
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Settings {
+  pub dry_run: bool, // Compute intents without dispatching them or saving memory
+  pub log_intents: bool,
+  pub save_interval: u32, // Ticks between saves while memory is not dirty
+}
+
+impl Default for Settings {
+  fn default() -> Self {
+    Self {
+      dry_run: false,
+      log_intents: false,
+      save_interval: MEMORY_SAVE_INTERVAL,
+    }
+  }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Memory {
+  pub version: MemVersion,
+  #[serde(default)]
+  pub settings: Settings,
+  #[serde(default)]
+  pub recorder: crate::recorder::Recorder,
+  #[serde(default)]
+  pub storage: storage::SegmentStorage,
+  pub hive : crate::hive::Hive,
+
+  #[serde(skip)]
+  pub dirty: bool, // Save at the end of this tick regardless of the save interval
+}
+
+impl Memory {
+  pub fn new(world: &dyn GameWorld) -> Memory {
+    Memory {
+      version: MEM_VERSION,
+      settings: Settings::default(),
+      recorder: crate::recorder::Recorder::default(),
+      storage: storage::SegmentStorage::default(),
+
+      hive : crate::hive::Hive::new(world, world.room_names().into_iter().next().unwrap()),
+
+      dirty: true,
+    }
+  }
+
+  pub fn mark_dirty(&mut self) {
+    self.dirty = true;
+  }
+
+  pub fn run(&mut self, world: &mut dyn GameWorld) {
+    self.hive.run(world)
+  }
+
+  // Tasks handed to a creep that just started spawning and the index of freshly written shards must survive a global
+  // reset
+  pub fn note_dispatched(&mut self, results: &[IntentResult]) {
+    if results.iter().any(|result| matches!(result.intent, Intent::SpawnCreep { .. }) && result.return_code == Some(screeps::ReturnCode::Ok)) {
+      self.mark_dirty();
+    }
+    if self.storage.take_changed() {
+      self.mark_dirty();
+    }
+  }
+}
+
+// Empty RawMemory starts a new hive, anything unreadable is an error so the caller can fall back to a backup
+pub fn load(world: &dyn GameWorld) -> Result<Memory,String> {
+  let serialized = world.raw_memory();
+  if serialized.is_empty() {
+    return Ok(Memory::new(world));
+  }
+  decode(&serialized)
+}
+
+pub fn decode(serialized: &str) -> Result<Memory,String> {
+  // Current version parses straight into Memory in either codec, anything else goes through the migrations
+  match codec::decode::<Memory>(serialized) {
+    Ok(memory) if memory.version == MEM_VERSION => Ok(memory),
+    _ => migrations::migrate_str(serialized).map(|mut memory| {
+      memory.mark_dirty();
+      memory
+    }),
+  }
+}
+
+// Returns what was written to RawMemory
+pub fn save(world: &mut dyn GameWorld, memory: &Memory) -> Option<String> {
+  match codec::encode(memory, codec::SAVE_CODEC) {
+    Ok(serialized) => {
+      world.set_raw_memory(&serialized);
+      Some(serialized)
+    },
+    Err(e) => {
+      console::warn(format!("[ memory ] Unable to save memory: {}", e));
+      None
+    },
+  }
+}
\ No newline at end of file
@@ -0,0 +1,169 @@
+use std::collections::{BTreeSet, HashMap};
+use std::ops::Range;
+
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
+
+use crate::console;
+use crate::constants::{BACKUP_SEGMENT_START, BACKUP_SLOTS, BACKUP_SLOT_SEGMENTS, MAX_ACTIVE_SEGMENTS, RECORDER_SEGMENT_COUNT, RECORDER_SEGMENT_START, SEGMENT_SIZE_LIMIT, STORAGE_SEGMENT_COUNT, STORAGE_SEGMENT_START};
+use crate::world::GameWorld;
+use super::codec;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SegmentOwner {
+  Storage,
+  Backup,
+  Recorder,
+}
+
+// Which subsystem owns which segments, (owner, first segment, segment count). Nothing writes outside its own range.
+pub const SEGMENT_REGISTRY: &[(SegmentOwner,u8,u8)] = &[
+  (SegmentOwner::Storage, STORAGE_SEGMENT_START, STORAGE_SEGMENT_COUNT),
+  (SegmentOwner::Backup, BACKUP_SEGMENT_START, BACKUP_SLOTS * BACKUP_SLOT_SEGMENTS),
+  (SegmentOwner::Recorder, RECORDER_SEGMENT_START, RECORDER_SEGMENT_COUNT),
+];
+
+pub fn segments_of(owner: SegmentOwner) -> Range<u8> {
+  SEGMENT_REGISTRY.iter()
+    .find(|(registered_owner, _, _)| *registered_owner == owner)
+    .map(|(_, start, count)| *start..(start + count))
+    .unwrap_or(0..0)
+}
+
+pub fn owner_of(segment_id: u8) -> Option<SegmentOwner> {
+  SEGMENT_REGISTRY.iter()
+    .find(|(_, start, count)| (*start..(start + count)).contains(&segment_id))
+    .map(|(owner, _, _)| *owner)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ShardInfo {
+  pub segments: Vec<u8>, // In order, the serialized shard is their contents joined together
+  pub length: usize, // Characters
+  pub saved: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShardLoad<T> {
+  Loaded(T),
+  Pending, // Its segments have been requested and will be readable next tick
+  Missing,
+}
+
+// Keeps state that is too big or too rarely used for RawMemory in named shards spread across the storage segments.
+// Only the index lives in Memory, shards are read on demand and their segments activated a tick ahead.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SegmentStorage {
+  pub shards: HashMap<String,ShardInfo>,
+
+  #[serde(skip)]
+  requested: BTreeSet<u8>,
+  #[serde(skip)]
+  changed: bool, // The index changed and has to reach RawMemory before a global reset can lose it
+}
+
+impl SegmentStorage {
+  pub fn load<T: DeserializeOwned>(&mut self, world: &dyn GameWorld, name: &str) -> ShardLoad<T> {
+    let shard = match self.shards.get(name) {
+      Some(shard) => shard.to_owned(),
+      None => return ShardLoad::Missing,
+    };
+
+    let mut serialized = String::with_capacity(shard.length);
+    for segment_id in shard.segments.iter() {
+      match world.segment(*segment_id) {
+        Some(data) => serialized.push_str(&data),
+        None => {
+          self.request(&shard.segments);
+          return ShardLoad::Pending;
+        },
+      }
+    }
+
+    if serialized.chars().count() != shard.length {
+      console::warn(format!("[ storage ] Shard {} is {} characters long instead of {}", name, serialized.chars().count(), shard.length));
+      return ShardLoad::Missing;
+    }
+    match codec::decode(&serialized) {
+      Ok(value) => ShardLoad::Loaded(value),
+      Err(e) => {
+        console::warn(format!("[ storage ] Unable to decode shard {}: {}", name, e));
+        ShardLoad::Missing
+      },
+    }
+  }
+
+  // New contents go to segments the shard was not using so the old copy stays intact until the index is saved
+  pub fn store<T: Serialize>(&mut self, world: &mut dyn GameWorld, name: &str, value: &T) -> Result<(),String> {
+    let serialized = codec::encode(value, codec::SAVE_CODEC)?;
+    let chunks = split_chunks(&serialized, SEGMENT_SIZE_LIMIT);
+    if chunks.len() > MAX_ACTIVE_SEGMENTS {
+      return Err(format!("Shard {} needs {} segments, at most {} can be read in a tick", name, chunks.len(), MAX_ACTIVE_SEGMENTS));
+    }
+    if chunks.len() > world.segment_writes_left() {
+      return Err(format!("Shard {} needs {} segments, only {} more can be written this tick", name, chunks.len(), world.segment_writes_left()));
+    }
+
+    let in_use = self.shards.values().flat_map(|shard| shard.segments.iter().copied()).collect::<BTreeSet<_>>();
+    let segments = segments_of(SegmentOwner::Storage)
+      .filter(|segment_id| !in_use.contains(segment_id))
+      .take(chunks.len())
+      .collect::<Vec<_>>();
+    if segments.len() < chunks.len() {
+      return Err(format!("Shard {} needs {} segments but only {} are free", name, chunks.len(), segments.len()));
+    }
+
+    for (segment_id, chunk) in segments.iter().zip(chunks.iter()) {
+      world.set_segment(*segment_id, chunk);
+    }
+    self.shards.insert(name.to_owned(), ShardInfo {
+      segments,
+      length: serialized.chars().count(),
+      saved: world.time(),
+    });
+    self.changed = true;
+    Ok(())
+  }
+
+  pub fn remove(&mut self, name: &str) {
+    if self.shards.remove(name).is_some() {
+      self.changed = true;
+    }
+  }
+
+  // Other owners use this to read their own segments, everything requested in a tick is activated together
+  pub fn request(&mut self, segment_ids: &[u8]) {
+    self.requested.extend(segment_ids.iter().copied());
+  }
+
+  // Call once at the end of the tick. Requests beyond the active segment limit wait until they are asked for again.
+  pub fn activate(&mut self, world: &mut dyn GameWorld) {
+    if self.requested.is_empty() {
+      return;
+    }
+    let segment_ids = self.requested.iter().copied().take(MAX_ACTIVE_SEGMENTS).collect::<Vec<_>>();
+    world.set_active_segments(&segment_ids);
+    self.requested.clear();
+  }
+
+  pub fn take_changed(&mut self) -> bool {
+    std::mem::take(&mut self.changed)
+  }
+}
+
+pub(crate) fn split_chunks(serialized: &str, chunk_length: usize) -> Vec<&str> {
+  let mut chunks = vec![];
+  let mut start = 0;
+  let mut length = 0;
+  for (index, _) in serialized.char_indices() {
+    if length == chunk_length {
+      chunks.push(&serialized[start..index]);
+      start = index;
+      length = 0;
+    }
+    length += 1;
+  }
+  if length > 0 {
+    chunks.push(&serialized[start..]);
+  }
+  chunks
+}
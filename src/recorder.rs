@@ -0,0 +1,126 @@
+use std::collections::VecDeque;
+
+use serde::{Serialize, Deserialize};
+
+use crate::console;
+use crate::constants::{RECORDER_SEGMENT_START, RECORDER_SEGMENT_COUNT, SEGMENT_SIZE_LIMIT};
+use crate::memory::{Memory, storage::{SegmentOwner, segments_of}};
+use crate::world::{GameWorld, Intent, IntentResult, WorldSnapshot};
+
+// One tick of input and output: the memory and game state the hive saw and the intents it emitted
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TickRecord {
+  pub memory: String,
+  pub world: WorldSnapshot,
+  pub intents: Vec<Intent>,
+  #[serde(default)]
+  pub segments: Vec<(u8,String)>, // Storage segments that were readable, the hive's only other input
+}
+
+// Records ticks into a ring of RawMemory segments while `remaining_ticks` is above zero
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Recorder {
+  pub remaining_ticks: u32,
+  pub recorded: VecDeque<(u8,u32)>, // (segment, tick), oldest first
+}
+
+impl Recorder {
+  pub fn capture(&self, world: &dyn GameWorld, memory: &Memory) -> Option<TickRecord> {
+    if self.remaining_ticks == 0 {
+      return None;
+    }
+
+    match serde_json::to_string(memory) {
+      Ok(serialized) => Some(TickRecord {
+        memory: serialized,
+        world: WorldSnapshot::capture(world),
+        intents: vec![],
+        segments: segments_of(SegmentOwner::Storage)
+          .filter_map(|segment_id| world.segment(segment_id).map(|data| (segment_id, data)))
+          .collect(),
+      }),
+      Err(e) => {
+        console::warn(format!("[ recorder ] Unable to serialize memory: {}", e));
+        None
+      },
+    }
+  }
+
+  pub fn store(&mut self, world: &mut dyn GameWorld, mut record: TickRecord, results: &[IntentResult]) {
+    self.remaining_ticks = self.remaining_ticks.saturating_sub(1);
+    record.intents = results.iter().map(|result| result.intent.to_owned()).collect();
+
+    let serialized = match serde_json::to_string(&record) {
+      Ok(serialized) if serialized.len() <= SEGMENT_SIZE_LIMIT => serialized,
+      Ok(serialized) => {
+        console::warn(format!("[ recorder ] Tick {} is {} bytes which does not fit in a segment", record.world.time, serialized.len()));
+        return;
+      },
+      Err(e) => {
+        console::warn(format!("[ recorder ] Unable to serialize tick {}: {}", record.world.time, e));
+        return;
+      },
+    };
+
+    if world.segment_writes_left() == 0 {
+      console::warn(format!("[ recorder ] Tick {} was not recorded, no segment writes are left this tick", record.world.time));
+      return;
+    }
+
+    let segment_id = match self.recorded.back() {
+      Some((last_segment_id, _)) => RECORDER_SEGMENT_START + (last_segment_id - RECORDER_SEGMENT_START + 1) % RECORDER_SEGMENT_COUNT,
+      None => RECORDER_SEGMENT_START,
+    };
+    self.recorded.retain(|(recorded_segment_id, _)| *recorded_segment_id != segment_id);
+    self.recorded.push_back((segment_id, record.world.time));
+
+    world.set_segment(segment_id, &serialized);
+  }
+}
+
+// Re-runs the hive against a recorded tick without dispatching anything and returns the intents it decided on
+#[cfg(not(target_arch = "wasm32"))]
+pub fn replay(record: &TickRecord) -> Vec<Intent> {
+  let mut world = crate::world::MemoryWorld::from_snapshot(&record.world);
+  world.raw_memory = record.memory.to_owned();
+  for (segment_id, data) in record.segments.iter() {
+    world.segments.insert(*segment_id, data.to_owned());
+    world.active_segments.insert(*segment_id);
+  }
+
+  let mut mem = crate::memory::load(&world).unwrap_or_else(|e| {
+    console::warn(format!("[ recorder ] Unable to load recorded memory, starting over: {}", e));
+    crate::memory::Memory::new(&world)
+  });
+  let mut intents = crate::world::IntentQueue::new(&mut world, true, false);
+  mem.run(&mut intents);
+  intents.intents().to_vec()
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct IntentDiff {
+  pub missing: Vec<Intent>, // Recorded but not replayed
+  pub extra: Vec<Intent>, // Replayed but not recorded
+}
+
+impl IntentDiff {
+  // Order is ignored, only what the hive decided to do is compared
+  pub fn between(recorded: &[Intent], replayed: &[Intent]) -> Self {
+    let mut extra = replayed.to_vec();
+    let mut missing = vec![];
+    for intent in recorded {
+      match extra.iter().position(|replayed_intent| replayed_intent == intent) {
+        Some(index) => { extra.remove(index); },
+        None => missing.push(intent.to_owned()),
+      }
+    }
+    Self {
+      missing,
+      extra,
+    }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.missing.is_empty() && self.extra.is_empty()
+  }
+}
@@ -0,0 +1,209 @@
+use std::{collections::HashSet, path::Path};
+
+use screeps::{Part, RawObjectId, RoomName, StructureType};
+use serde::{Serialize, Deserialize};
+
+use crate::constants::ROOM_SIZE;
+use crate::sim::Simulation;
+use crate::world::{MemoryWorld, new_position, memory_world::WorldStats};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct ScenarioPos {
+  pub room: RoomName,
+  pub x: u8,
+  pub y: u8,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ScenarioRoom {
+  pub name: RoomName,
+  // One string per row, '#' is a wall, '~' is a swamp and anything else is plain
+  #[serde(default)]
+  pub terrain: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ScenarioSource {
+  pub id: Option<RawObjectId>,
+  pub pos: ScenarioPos,
+  #[serde(default = "default_source_capacity")]
+  pub energy_capacity: u32,
+}
+
+fn default_source_capacity() -> u32 {
+  screeps::constants::SOURCE_ENERGY_CAPACITY
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ScenarioStructure {
+  pub id: Option<RawObjectId>,
+  pub structure_type: StructureType,
+  pub pos: ScenarioPos,
+  #[serde(default)]
+  pub energy: u32,
+  #[serde(default)]
+  pub energy_capacity: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ScenarioCreep {
+  pub id: Option<RawObjectId>,
+  pub name: String,
+  pub pos: ScenarioPos,
+  pub body: Vec<Part>,
+  #[serde(default)]
+  pub energy: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Expectation {
+  EnergyDelivered { at_least: u32, by_tick: u32 },
+  EnergyHarvested { at_least: u32, by_tick: u32 },
+  CreepExists { name: String, by_tick: u32 },
+  NoTasksForDeadCreeps,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Scenario {
+  pub name: String,
+  pub ticks: u32,
+  #[serde(default)]
+  pub rooms: Vec<ScenarioRoom>,
+  #[serde(default)]
+  pub sources: Vec<ScenarioSource>,
+  #[serde(default)]
+  pub structures: Vec<ScenarioStructure>,
+  #[serde(default)]
+  pub creeps: Vec<ScenarioCreep>,
+  // Serialized `Memory` to start from, the hive bootstraps itself when omitted
+  pub memory: Option<serde_json::Value>,
+  #[serde(default)]
+  pub expectations: Vec<Expectation>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ScenarioReport {
+  pub name: String,
+  pub ticks: u32,
+  pub stats: WorldStats,
+  pub failures: Vec<String>,
+}
+
+impl ScenarioReport {
+  pub fn passed(&self) -> bool {
+    self.failures.is_empty()
+  }
+}
+
+impl Scenario {
+  pub fn load<P: AsRef<Path>>(path: P) -> Result<Scenario,String> {
+    let contents = std::fs::read_to_string(path.as_ref()).map_err(|e| format!("Unable to read {:?}: {}", path.as_ref(), e))?;
+    serde_json::from_str(&contents).map_err(|e| format!("Unable to parse {:?}: {}", path.as_ref(), e))
+  }
+
+  pub fn build_world(&self) -> Result<MemoryWorld,String> {
+    let mut world = MemoryWorld::new();
+
+    for room in self.rooms.iter() {
+      if room.terrain.len() > ROOM_SIZE.1 as usize || room.terrain.iter().any(|row| row.chars().count() > ROOM_SIZE.0 as usize) {
+        return Err(format!("Terrain of {} is larger than a room", room.name));
+      }
+      world.set_room_terrain(room.name, &room.terrain);
+    }
+
+    // Explicit ids go first so generated ones can't claim them
+    let mut sources = self.sources.iter().collect::<Vec<_>>();
+    sources.sort_by_key(|source| source.id.is_none());
+    for source in sources {
+      let id = source.id.unwrap_or_else(|| world.generate_id());
+      world.insert_source(id, Self::position(&source.pos)?, source.energy_capacity);
+    }
+
+    let mut structures = self.structures.iter().collect::<Vec<_>>();
+    structures.sort_by_key(|structure| structure.id.is_none());
+    for structure in structures {
+      let id = structure.id.unwrap_or_else(|| world.generate_id());
+      world.insert_structure(id, structure.structure_type, Self::position(&structure.pos)?, structure.energy, structure.energy_capacity);
+    }
+
+    let mut creeps = self.creeps.iter().collect::<Vec<_>>();
+    creeps.sort_by_key(|creep| creep.id.is_none());
+    for creep in creeps {
+      let id = creep.id.unwrap_or_else(|| world.generate_id());
+      world.insert_creep(id, &creep.name, Self::position(&creep.pos)?, creep.body.to_owned());
+      if let Some(state) = world.creeps.get_mut(&creep.name) {
+        state.energy = creep.energy.min(state.energy_capacity);
+      }
+    }
+
+    if let Some(memory) = self.memory.as_ref() {
+      world.raw_memory = memory.to_string();
+    }
+
+    Ok(world)
+  }
+
+  fn position(pos: &ScenarioPos) -> Result<screeps::Position,String> {
+    let (width, height) = crate::constants::ROOM_SIZE;
+    if (pos.x as u32) < width && (pos.y as u32) < height {
+      Ok(new_position(pos.x, pos.y, pos.room))
+    } else {
+      Err(format!("Position {:?} is outside of the room", pos))
+    }
+  }
+
+  pub fn run(&self) -> Result<ScenarioReport,String> {
+    let mut simulation = Simulation::new(self.build_world()?);
+    let mut satisfied = vec![false; self.expectations.len()];
+    let mut failures = vec![];
+
+    for _ in 0..self.ticks {
+      let tick = simulation.world.time;
+      let creeps_at_start = simulation.world.creeps.keys().cloned().collect::<HashSet<_>>();
+      let memory = simulation.run_tick();
+      let world = &simulation.world;
+
+      for (index, expectation) in self.expectations.iter().enumerate() {
+        match expectation {
+          Expectation::EnergyDelivered { at_least, by_tick } if tick <= *by_tick => {
+            satisfied[index] |= world.stats.energy_delivered >= *at_least;
+          },
+          Expectation::EnergyHarvested { at_least, by_tick } if tick <= *by_tick => {
+            satisfied[index] |= world.stats.energy_harvested >= *at_least;
+          },
+          Expectation::CreepExists { name, by_tick } if tick <= *by_tick => {
+            satisfied[index] |= world.creeps.contains_key(name);
+          },
+          Expectation::NoTasksForDeadCreeps => {
+            let busy_creep_names = memory.iter().flat_map(|memory| {
+              let tasks = &memory.hive.tasks;
+              tasks.task_list.keys().map(String::as_str)
+                .chain(tasks.groups.iter().flat_map(|(leader_name, group_task)| group_task.members(leader_name)))
+            });
+            for creep_name in busy_creep_names {
+              if !creeps_at_start.contains(creep_name) {
+                failures.push(format!("Tick {}: task left for dead creep {:?}", tick, creep_name));
+              }
+            }
+            satisfied[index] = true;
+          },
+          _ => {},
+        }
+      }
+    }
+
+    for (expectation, satisfied) in self.expectations.iter().zip(satisfied) {
+      if !satisfied {
+        failures.push(format!("Expectation not met: {:?}", expectation));
+      }
+    }
+
+    Ok(ScenarioReport {
+      name: self.name.to_owned(),
+      ticks: self.ticks,
+      stats: simulation.world.stats.to_owned(),
+      failures,
+    })
+  }
+}
@@ -0,0 +1,63 @@
+use crate::memory::{self, cache::MemoryCache};
+use crate::world::{IntentQueue, MemoryWorld};
+
+// Drives the hive against a MemoryWorld exactly like `game_loop` drives it against the live game
+pub struct Simulation {
+  pub world: MemoryWorld,
+  cache: MemoryCache,
+}
+
+impl Simulation {
+  pub fn new(world: MemoryWorld) -> Self {
+    Self {
+      world,
+      cache: MemoryCache::new(),
+    }
+  }
+
+  // Drops the heap cache as a global reset would
+  pub fn reset_global(&mut self) {
+    self.cache = MemoryCache::new();
+  }
+
+  // Returns the memory as it was at the end of the tick, None when the hive sat the tick out waiting on a backup
+  pub fn run_tick(&mut self) -> Option<memory::Memory> {
+    let mem = match self.cache.get(&mut self.world) {
+      Some(mem) => mem,
+      None => {
+        self.world.end_tick();
+        return None;
+      },
+    };
+    let record = mem.recorder.capture(&self.world, mem);
+
+    let mut intents = IntentQueue::new(&mut self.world, mem.settings.dry_run, mem.settings.log_intents);
+    mem.run(&mut intents);
+    let results = intents.dispatch();
+    mem.storage.activate(&mut self.world);
+    mem.note_dispatched(&results);
+
+    if let Some(record) = record {
+      mem.recorder.store(&mut self.world, record, &results);
+    }
+
+    let mem = mem.to_owned();
+    if mem.settings.dry_run {
+      self.cache.invalidate();
+    } else {
+      self.cache.persist(&mut self.world);
+    }
+
+    self.world.end_tick();
+
+    Some(mem)
+  }
+
+  pub fn run<F>(&mut self, ticks: u32, mut after_tick: F)
+  where F: FnMut(&MemoryWorld) {
+    for _ in 0..ticks {
+      self.run_tick();
+      after_tick(&self.world);
+    }
+  }
+}
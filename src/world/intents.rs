@@ -0,0 +1,565 @@
+use screeps::{Direction, Part, Position, RawObjectId, ResourceType, ReturnCode, RoomName, StructureType, Terrain};
+use serde::{Serialize, Deserialize};
+
+use crate::console;
+use crate::constants::CREEP_PART_LIMIT;
+use super::{GameWorld, RoomState, CreepState, SourceState, StructureState, ConstructionSiteState, ResourceState};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Intent {
+  MoveDirection { creep: String, direction: Direction },
+  MoveTo { creep: String, target: Position },
+  MovePulledBy { creep: String, puller: String },
+  Pull { creep: String, pulled: String },
+  Harvest { creep: String, source: RawObjectId },
+  Pickup { creep: String, resource: RawObjectId },
+  Transfer { creep: String, target: RawObjectId, resource_type: ResourceType, amount: Option<u32> },
+  Withdraw { creep: String, target: RawObjectId, resource_type: ResourceType, amount: Option<u32> },
+  Drop { creep: String, resource_type: ResourceType, amount: Option<u32> },
+  Build { creep: String, site: RawObjectId },
+  Repair { creep: String, target: RawObjectId },
+  UpgradeController { creep: String, controller: RawObjectId },
+  Dismantle { creep: String, target: RawObjectId },
+  ClaimController { creep: String, controller: RawObjectId },
+  ReserveController { creep: String, controller: RawObjectId },
+  Heal { creep: String, target: RawObjectId },
+  Attack { creep: String, target: RawObjectId },
+  RangedAttack { creep: String, target: RawObjectId },
+  SpawnCreep { spawn: RawObjectId, name: String, body: Vec<Part> },
+  CreateConstructionSite { pos: Position, structure_type: StructureType },
+  TransferEnergy { link: RawObjectId, target: RawObjectId, amount: Option<u32> },
+}
+
+// Issuing a second intent in the same slot replaces the first, the way repeated calls to the same creep method do in game
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IntentSlot {
+  Move,
+  Pull,
+  Harvest,
+  Pickup,
+  Transfer,
+  Withdraw,
+  Drop,
+  Build,
+  Repair,
+  UpgradeController,
+  Dismantle,
+  ClaimController,
+  ReserveController,
+  Heal,
+  Attack,
+  RangedAttack,
+  Spawn,
+  ConstructionSite,
+  TransferEnergy,
+}
+
+impl IntentSlot {
+  // Actions that cancel this one when a creep issues both in the same tick, from the server's intent priorities
+  pub fn overridden_by(self) -> &'static [IntentSlot] {
+    match self {
+      IntentSlot::Harvest => &[IntentSlot::Attack, IntentSlot::Build, IntentSlot::Repair, IntentSlot::Dismantle, IntentSlot::Heal],
+      IntentSlot::Attack => &[IntentSlot::Build, IntentSlot::Repair, IntentSlot::Dismantle, IntentSlot::Heal],
+      IntentSlot::Build => &[IntentSlot::Repair, IntentSlot::Dismantle, IntentSlot::Heal],
+      IntentSlot::Repair => &[IntentSlot::Dismantle, IntentSlot::Heal],
+      IntentSlot::Dismantle => &[IntentSlot::Heal],
+      IntentSlot::RangedAttack => &[IntentSlot::Build, IntentSlot::Repair],
+      _ => &[],
+    }
+  }
+
+  // Whether a creep can issue both in one tick and have both carried out
+  pub fn compatible_with(self, other: IntentSlot) -> bool {
+    self != other && !self.overridden_by().contains(&other) && !other.overridden_by().contains(&self)
+  }
+}
+
+impl Intent {
+  // Creep name, the spawn or link id for structure intents, or the tile for construction sites
+  pub fn actor(&self) -> String {
+    match self {
+      Intent::MoveDirection { creep, .. } |
+      Intent::MoveTo { creep, .. } |
+      Intent::MovePulledBy { creep, .. } |
+      Intent::Pull { creep, .. } |
+      Intent::Harvest { creep, .. } |
+      Intent::Pickup { creep, .. } |
+      Intent::Transfer { creep, .. } |
+      Intent::Withdraw { creep, .. } |
+      Intent::Drop { creep, .. } |
+      Intent::Build { creep, .. } |
+      Intent::Repair { creep, .. } |
+      Intent::UpgradeController { creep, .. } |
+      Intent::Dismantle { creep, .. } |
+      Intent::ClaimController { creep, .. } |
+      Intent::ReserveController { creep, .. } |
+      Intent::Heal { creep, .. } |
+      Intent::Attack { creep, .. } |
+      Intent::RangedAttack { creep, .. } => creep.to_owned(),
+      Intent::SpawnCreep { spawn, .. } => spawn.to_string(),
+      Intent::CreateConstructionSite { pos, .. } => pos.to_string(),
+      Intent::TransferEnergy { link, .. } => link.to_string(),
+    }
+  }
+
+  pub fn slot(&self) -> IntentSlot {
+    match self {
+      Intent::MoveDirection { .. } | Intent::MoveTo { .. } | Intent::MovePulledBy { .. } => IntentSlot::Move,
+      Intent::Pull { .. } => IntentSlot::Pull,
+      Intent::Harvest { .. } => IntentSlot::Harvest,
+      Intent::Pickup { .. } => IntentSlot::Pickup,
+      Intent::Transfer { .. } => IntentSlot::Transfer,
+      Intent::Withdraw { .. } => IntentSlot::Withdraw,
+      Intent::Drop { .. } => IntentSlot::Drop,
+      Intent::Build { .. } => IntentSlot::Build,
+      Intent::Repair { .. } => IntentSlot::Repair,
+      Intent::UpgradeController { .. } => IntentSlot::UpgradeController,
+      Intent::Dismantle { .. } => IntentSlot::Dismantle,
+      Intent::ClaimController { .. } => IntentSlot::ClaimController,
+      Intent::ReserveController { .. } => IntentSlot::ReserveController,
+      Intent::Heal { .. } => IntentSlot::Heal,
+      Intent::Attack { .. } => IntentSlot::Attack,
+      Intent::RangedAttack { .. } => IntentSlot::RangedAttack,
+      Intent::SpawnCreep { .. } => IntentSlot::Spawn,
+      Intent::CreateConstructionSite { .. } => IntentSlot::ConstructionSite,
+      Intent::TransferEnergy { .. } => IntentSlot::TransferEnergy,
+    }
+  }
+
+  pub fn dispatch(&self, world: &mut dyn GameWorld) -> ReturnCode {
+    match self {
+      Intent::MoveDirection { creep, direction } => world.creep_move_direction(creep, *direction),
+      Intent::MoveTo { creep, target } => world.creep_move_to(creep, *target),
+      Intent::MovePulledBy { creep, puller } => world.creep_move_pulled_by(creep, puller),
+      Intent::Pull { creep, pulled } => world.creep_pull(creep, pulled),
+      Intent::Harvest { creep, source } => world.creep_harvest(creep, *source),
+      Intent::Pickup { creep, resource } => world.creep_pickup(creep, *resource),
+      Intent::Transfer { creep, target, resource_type, amount } => world.creep_transfer(creep, *target, *resource_type, *amount),
+      Intent::Withdraw { creep, target, resource_type, amount } => world.creep_withdraw(creep, *target, *resource_type, *amount),
+      Intent::Drop { creep, resource_type, amount } => world.creep_drop(creep, *resource_type, *amount),
+      Intent::Build { creep, site } => world.creep_build(creep, *site),
+      Intent::Repair { creep, target } => world.creep_repair(creep, *target),
+      Intent::UpgradeController { creep, controller } => world.creep_upgrade_controller(creep, *controller),
+      Intent::Dismantle { creep, target } => world.creep_dismantle(creep, *target),
+      Intent::ClaimController { creep, controller } => world.creep_claim_controller(creep, *controller),
+      Intent::ReserveController { creep, controller } => world.creep_reserve_controller(creep, *controller),
+      Intent::Heal { creep, target } => world.creep_heal(creep, *target),
+      Intent::Attack { creep, target } => world.creep_attack(creep, *target),
+      Intent::RangedAttack { creep, target } => world.creep_ranged_attack(creep, *target),
+      Intent::SpawnCreep { spawn, name, body } => world.spawn_creep(*spawn, body, name),
+      Intent::CreateConstructionSite { pos, structure_type } => world.create_construction_site(*pos, *structure_type),
+      Intent::TransferEnergy { link, target, amount } => world.link_transfer_energy(*link, *target, *amount),
+    }
+  }
+
+  // Return code the game is expected to give, worked out only from what the world shows. Both worlds validate with
+  // this so a replay accepts and refuses the same intents the live tick did.
+  pub fn check(&self, world: &dyn GameWorld) -> ReturnCode {
+    let active_creep = |creep_name: &str| match world.creep(creep_name) {
+      Some(creep) if creep.spawning => Err(ReturnCode::Busy),
+      Some(creep) => Ok(creep),
+      None => Err(ReturnCode::NotFound),
+    };
+    let creep_by_id = |id: RawObjectId| world.creep_names().iter().filter_map(|creep_name| world.creep(creep_name)).find(|creep| creep.id == Some(id));
+    let structure_pos = |id: RawObjectId| world.structure(id).map(|structure| structure.pos);
+    let controller_pos = |id: RawObjectId| world.structure(id).filter(|structure| structure.structure_type == StructureType::Controller).map(|structure| structure.pos);
+    // The creep, its body and its range to the target, shared by every action that works on a target
+    let action = |creep_name: &str, target_pos: Option<Position>, part: Part, range: u32| match (active_creep(creep_name), target_pos) {
+      (Err(return_code), _) => Err(return_code),
+      (_, None) => Err(ReturnCode::InvalidTarget),
+      (Ok(creep), _) if creep.count_parts(part) == 0 => Err(ReturnCode::NoBodypart),
+      (Ok(creep), Some(pos)) if creep.pos.get_range_to(&pos) > range => Err(ReturnCode::NotInRange),
+      (Ok(creep), _) => Ok(creep),
+    };
+    let needs_energy = |result: Result<CreepState,ReturnCode>| match result {
+      Ok(creep) if creep.energy == 0 => ReturnCode::NotEnough,
+      Ok(_) => ReturnCode::Ok,
+      Err(return_code) => return_code,
+    };
+
+    match self {
+      Intent::MoveDirection { creep, .. } | Intent::MoveTo { creep, .. } => {
+        match active_creep(creep) {
+          Ok(creep) if creep.count_parts(Part::Move) == 0 => ReturnCode::NoBodypart,
+          Ok(creep) if creep.fatigue > 0 => ReturnCode::Tired,
+          Ok(_) => ReturnCode::Ok,
+          Err(return_code) => return_code,
+        }
+      },
+      Intent::MovePulledBy { creep, puller: other } | Intent::Pull { creep, pulled: other } => {
+        match (active_creep(creep), world.creep(other)) {
+          (Err(return_code), _) => return_code,
+          (_, None) => ReturnCode::InvalidTarget,
+          (Ok(creep), Some(other)) if !creep.pos.is_near_to(&other.pos) => ReturnCode::NotInRange,
+          _ => ReturnCode::Ok,
+        }
+      },
+      Intent::Harvest { creep, source } => {
+        let source = world.source(*source);
+        match action(creep, source.as_ref().map(|source| source.pos), Part::Work, 1) {
+          Err(return_code) => return_code,
+          Ok(_) if source.as_ref().map_or(0, |source| source.energy) == 0 => ReturnCode::NotEnough,
+          Ok(_) => ReturnCode::Ok,
+        }
+      },
+      Intent::Pickup { creep, resource } => {
+        match (active_creep(creep), world.object_pos(*resource)) {
+          (Err(return_code), _) => return_code,
+          (_, None) => ReturnCode::InvalidTarget,
+          (Ok(creep), _) if creep.free_capacity() == 0 => ReturnCode::Full,
+          (Ok(creep), Some(pos)) if !creep.pos.is_near_to(&pos) => ReturnCode::NotInRange,
+          _ => ReturnCode::Ok,
+        }
+      },
+      Intent::Transfer { creep, target, resource_type, amount } => {
+        let target = world.structure(*target).map(|structure| (structure.pos, structure.free_capacity()))
+          .or_else(|| creep_by_id(*target).map(|creep| (creep.pos, creep.free_capacity())));
+        match (active_creep(creep), target) {
+          (Err(return_code), _) => return_code,
+          (_, None) => ReturnCode::InvalidTarget,
+          _ if *resource_type != ResourceType::Energy => ReturnCode::NotEnough,
+          (Ok(creep), _) if creep.energy == 0 || amount.map(|amount| amount > creep.energy).unwrap_or(false) => ReturnCode::NotEnough,
+          (Ok(creep), Some((pos, _))) if !creep.pos.is_near_to(&pos) => ReturnCode::NotInRange,
+          (_, Some((_, free_capacity))) if free_capacity == 0 => ReturnCode::Full,
+          _ => ReturnCode::Ok,
+        }
+      },
+      Intent::Withdraw { creep, target, resource_type, amount } => {
+        match (active_creep(creep), world.structure(*target)) {
+          (Err(return_code), _) => return_code,
+          (_, None) => ReturnCode::InvalidTarget,
+          _ if *resource_type != ResourceType::Energy => ReturnCode::NotEnough,
+          (Ok(creep), _) if creep.free_capacity() == 0 => ReturnCode::Full,
+          (Ok(creep), Some(structure)) if !creep.pos.is_near_to(&structure.pos) => ReturnCode::NotInRange,
+          (_, Some(structure)) if structure.energy == 0 || amount.map(|amount| amount > structure.energy).unwrap_or(false) => ReturnCode::NotEnough,
+          _ => ReturnCode::Ok,
+        }
+      },
+      Intent::Drop { creep, resource_type, amount } => {
+        match active_creep(creep) {
+          Err(return_code) => return_code,
+          _ if *resource_type != ResourceType::Energy => ReturnCode::NotEnough,
+          Ok(creep) if creep.energy == 0 || amount.map(|amount| amount > creep.energy).unwrap_or(false) => ReturnCode::NotEnough,
+          Ok(_) => ReturnCode::Ok,
+        }
+      },
+      // Hits are not known to every world, these only check the creep, its body and the range to the target
+      Intent::Repair { creep, target } => needs_energy(action(creep, structure_pos(*target), Part::Work, 3)),
+      Intent::Dismantle { creep, target } => action(creep, structure_pos(*target), Part::Work, 1).err().unwrap_or(ReturnCode::Ok),
+      Intent::Heal { creep, target } => action(creep, creep_by_id(*target).map(|target| target.pos), Part::Heal, 1).err().unwrap_or(ReturnCode::Ok),
+      Intent::Attack { creep, target } => {
+        let target_pos = structure_pos(*target).or_else(|| creep_by_id(*target).map(|target| target.pos));
+        action(creep, target_pos, Part::Attack, 1).err().unwrap_or(ReturnCode::Ok)
+      },
+      Intent::RangedAttack { creep, target } => {
+        let target_pos = structure_pos(*target).or_else(|| creep_by_id(*target).map(|target| target.pos));
+        action(creep, target_pos, Part::RangedAttack, 3).err().unwrap_or(ReturnCode::Ok)
+      },
+      Intent::Build { creep, site } => needs_energy(action(creep, world.object_pos(*site), Part::Work, 3)),
+      // Neither ownership nor reservations are known to every world
+      Intent::UpgradeController { creep, controller } => needs_energy(action(creep, controller_pos(*controller), Part::Work, 3)),
+      Intent::ClaimController { creep, controller } | Intent::ReserveController { creep, controller } => {
+        action(creep, controller_pos(*controller), Part::Claim, 1).err().unwrap_or(ReturnCode::Ok)
+      },
+      Intent::CreateConstructionSite { pos, structure_type } => {
+        let room_name = pos.room_name();
+        let taken = world.find_structures(room_name).iter().any(|structure| structure.pos == *pos)
+          || world.find_construction_sites(room_name).iter().any(|site| site.pos == *pos);
+        if world.room(room_name).is_none() {
+          ReturnCode::NotInRange
+        } else if structure_type.construction_cost().is_none() {
+          ReturnCode::InvalidArgs
+        } else if taken || world.terrain(*pos) == Terrain::Wall {
+          ReturnCode::InvalidTarget
+        } else {
+          ReturnCode::Ok
+        }
+      },
+      Intent::TransferEnergy { link, target, amount } => {
+        match (world.structure(*link), world.structure(*target)) {
+          (Some(link), Some(target)) if link.structure_type == StructureType::Link && target.structure_type == StructureType::Link => {
+            if link.energy == 0 || amount.map(|amount| amount > link.energy).unwrap_or(false) {
+              ReturnCode::NotEnough
+            } else if link.pos.room_name() != target.pos.room_name() {
+              ReturnCode::NotInRange
+            } else if target.free_capacity() == 0 {
+              ReturnCode::Full
+            } else {
+              ReturnCode::Ok
+            }
+          },
+          _ => ReturnCode::InvalidTarget,
+        }
+      },
+      Intent::SpawnCreep { spawn, name, body } => {
+        let cost = body.iter().map(|part| part.cost()).sum::<u32>();
+        match world.structure(*spawn) {
+          Some(spawn) if spawn.structure_type != StructureType::Spawn => ReturnCode::InvalidTarget,
+          Some(spawn) if spawn.spawning => ReturnCode::Busy,
+          Some(_) if world.creep(name).is_some() => ReturnCode::NameExists,
+          Some(_) if body.is_empty() || body.len() as u32 > CREEP_PART_LIMIT => ReturnCode::InvalidArgs,
+          Some(spawn) if world.room(spawn.pos.room_name()).map(|room| room.energy_available).unwrap_or(0) < cost => ReturnCode::NotEnough,
+          Some(_) => ReturnCode::Ok,
+          None => ReturnCode::InvalidTarget,
+        }
+      },
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct IntentResult {
+  pub intent: Intent,
+  pub return_code: Option<ReturnCode>, // None when the intent was not dispatched
+}
+
+// Wraps a world so that every action is validated and queued instead of executed. Nothing reaches the game until
+// `dispatch` is called at the end of the tick, and in dry-run mode nothing reaches it at all.
+pub struct IntentQueue<'a> {
+  world: &'a mut dyn GameWorld,
+  intents: Vec<Intent>,
+  dry_run: bool,
+  verbose: bool,
+}
+
+impl<'a> IntentQueue<'a> {
+  pub fn new(world: &'a mut dyn GameWorld, dry_run: bool, verbose: bool) -> Self {
+    Self {
+      world,
+      intents: vec![],
+      dry_run,
+      verbose,
+    }
+  }
+
+  pub fn intents(&self) -> &[Intent] {
+    &self.intents
+  }
+
+  fn submit(&mut self, intent: Intent) -> ReturnCode {
+    let return_code = self.world.validate(&intent);
+    if return_code != ReturnCode::Ok {
+      return return_code;
+    }
+
+    let (actor, slot) = (intent.actor(), intent.slot());
+    if let Some(index) = self.intents.iter().position(|queued| queued.slot() == slot && queued.actor() == actor) {
+      let replaced = self.intents.remove(index);
+      if self.verbose {
+        console::debug(format!("[ intents ] {:?} replaced by {:?}", replaced, intent));
+      }
+    }
+    self.intents.push(intent);
+    ReturnCode::Ok
+  }
+
+  pub fn dispatch(self) -> Vec<IntentResult> {
+    let mut results = vec![];
+    for intent in self.intents {
+      let return_code = if self.dry_run {
+        None
+      } else {
+        Some(intent.dispatch(self.world))
+      };
+      match return_code {
+        Some(ReturnCode::Ok) | None => {
+          if self.verbose {
+            console::debug(format!("[ intents ] {:?} -> {:?}", intent, return_code));
+          }
+        },
+        Some(failure_code) => console::warn(format!("[ intents ] {:?} failed with {:?}", intent, failure_code)),
+      }
+      results.push(IntentResult {
+        intent,
+        return_code,
+      });
+    }
+    results
+  }
+}
+
+impl<'a> GameWorld for IntentQueue<'a> {
+  fn time(&self) -> u32 {
+    self.world.time()
+  }
+
+  fn raw_memory(&self) -> String {
+    self.world.raw_memory()
+  }
+
+  fn set_raw_memory(&mut self, serialized: &str) {
+    self.world.set_raw_memory(serialized)
+  }
+
+  fn room_names(&self) -> Vec<RoomName> {
+    self.world.room_names()
+  }
+
+  fn room(&self, room_name: RoomName) -> Option<RoomState> {
+    self.world.room(room_name)
+  }
+
+  fn creep(&self, creep_name: &str) -> Option<CreepState> {
+    self.world.creep(creep_name)
+  }
+
+  fn creep_names(&self) -> Vec<String> {
+    self.world.creep_names()
+  }
+
+  fn source(&self, source_id: RawObjectId) -> Option<SourceState> {
+    self.world.source(source_id)
+  }
+
+  fn find_sources(&self, room_name: RoomName) -> Vec<SourceState> {
+    self.world.find_sources(room_name)
+  }
+
+  fn structure(&self, structure_id: RawObjectId) -> Option<StructureState> {
+    self.world.structure(structure_id)
+  }
+
+  fn find_my_structures(&self, room_name: RoomName) -> Vec<StructureState> {
+    self.world.find_my_structures(room_name)
+  }
+
+  fn find_structures(&self, room_name: RoomName) -> Vec<StructureState> {
+    self.world.find_structures(room_name)
+  }
+
+  fn find_construction_sites(&self, room_name: RoomName) -> Vec<ConstructionSiteState> {
+    self.world.find_construction_sites(room_name)
+  }
+
+  fn terrain(&self, pos: Position) -> Terrain {
+    self.world.terrain(pos)
+  }
+
+  fn room_terrain(&self, room_name: RoomName) -> Vec<Terrain> {
+    self.world.room_terrain(room_name)
+  }
+
+  fn object_pos(&self, id: RawObjectId) -> Option<Position> {
+    self.world.object_pos(id)
+  }
+
+  fn find_path(&self, from: Position, to: Position, range: u32) -> Option<Vec<Position>> {
+    self.world.find_path(from, to, range)
+  }
+
+  fn look_for_energy(&self, pos: Position) -> Vec<ResourceState> {
+    self.world.look_for_energy(pos)
+  }
+
+  fn find_dropped_resources(&self, room_name: RoomName) -> Vec<ResourceState> {
+    self.world.find_dropped_resources(room_name)
+  }
+
+  fn segment(&self, segment_id: u8) -> Option<String> {
+    self.world.segment(segment_id)
+  }
+
+  fn set_segment(&mut self, segment_id: u8, data: &str) {
+    self.world.set_segment(segment_id, data)
+  }
+
+  fn segment_writes_left(&self) -> usize {
+    self.world.segment_writes_left()
+  }
+
+  fn set_active_segments(&mut self, segment_ids: &[u8]) {
+    self.world.set_active_segments(segment_ids)
+  }
+
+  fn notify(&mut self, message: &str) {
+    self.world.notify(message)
+  }
+
+  fn validate(&self, intent: &Intent) -> ReturnCode {
+    self.world.validate(intent)
+  }
+
+  fn creep_move_direction(&mut self, creep_name: &str, direction: Direction) -> ReturnCode {
+    self.submit(Intent::MoveDirection { creep: creep_name.to_owned(), direction })
+  }
+
+  fn creep_move_to(&mut self, creep_name: &str, target: Position) -> ReturnCode {
+    self.submit(Intent::MoveTo { creep: creep_name.to_owned(), target })
+  }
+
+  fn creep_harvest(&mut self, creep_name: &str, source_id: RawObjectId) -> ReturnCode {
+    self.submit(Intent::Harvest { creep: creep_name.to_owned(), source: source_id })
+  }
+
+  fn creep_pickup(&mut self, creep_name: &str, resource_id: RawObjectId) -> ReturnCode {
+    self.submit(Intent::Pickup { creep: creep_name.to_owned(), resource: resource_id })
+  }
+
+  fn creep_transfer(&mut self, creep_name: &str, target_id: RawObjectId, resource_type: ResourceType, amount: Option<u32>) -> ReturnCode {
+    self.submit(Intent::Transfer { creep: creep_name.to_owned(), target: target_id, resource_type, amount })
+  }
+
+  fn creep_withdraw(&mut self, creep_name: &str, target_id: RawObjectId, resource_type: ResourceType, amount: Option<u32>) -> ReturnCode {
+    self.submit(Intent::Withdraw { creep: creep_name.to_owned(), target: target_id, resource_type, amount })
+  }
+
+  fn creep_drop(&mut self, creep_name: &str, resource_type: ResourceType, amount: Option<u32>) -> ReturnCode {
+    self.submit(Intent::Drop { creep: creep_name.to_owned(), resource_type, amount })
+  }
+
+  fn creep_build(&mut self, creep_name: &str, site_id: RawObjectId) -> ReturnCode {
+    self.submit(Intent::Build { creep: creep_name.to_owned(), site: site_id })
+  }
+
+  fn creep_repair(&mut self, creep_name: &str, target_id: RawObjectId) -> ReturnCode {
+    self.submit(Intent::Repair { creep: creep_name.to_owned(), target: target_id })
+  }
+
+  fn creep_upgrade_controller(&mut self, creep_name: &str, controller_id: RawObjectId) -> ReturnCode {
+    self.submit(Intent::UpgradeController { creep: creep_name.to_owned(), controller: controller_id })
+  }
+
+  fn creep_dismantle(&mut self, creep_name: &str, target_id: RawObjectId) -> ReturnCode {
+    self.submit(Intent::Dismantle { creep: creep_name.to_owned(), target: target_id })
+  }
+
+  fn creep_claim_controller(&mut self, creep_name: &str, controller_id: RawObjectId) -> ReturnCode {
+    self.submit(Intent::ClaimController { creep: creep_name.to_owned(), controller: controller_id })
+  }
+
+  fn creep_reserve_controller(&mut self, creep_name: &str, controller_id: RawObjectId) -> ReturnCode {
+    self.submit(Intent::ReserveController { creep: creep_name.to_owned(), controller: controller_id })
+  }
+
+  fn creep_heal(&mut self, creep_name: &str, target_id: RawObjectId) -> ReturnCode {
+    self.submit(Intent::Heal { creep: creep_name.to_owned(), target: target_id })
+  }
+
+  fn creep_attack(&mut self, creep_name: &str, target_id: RawObjectId) -> ReturnCode {
+    self.submit(Intent::Attack { creep: creep_name.to_owned(), target: target_id })
+  }
+
+  fn creep_ranged_attack(&mut self, creep_name: &str, target_id: RawObjectId) -> ReturnCode {
+    self.submit(Intent::RangedAttack { creep: creep_name.to_owned(), target: target_id })
+  }
+
+  fn creep_pull(&mut self, creep_name: &str, pulled_name: &str) -> ReturnCode {
+    self.submit(Intent::Pull { creep: creep_name.to_owned(), pulled: pulled_name.to_owned() })
+  }
+
+  fn creep_move_pulled_by(&mut self, creep_name: &str, puller_name: &str) -> ReturnCode {
+    self.submit(Intent::MovePulledBy { creep: creep_name.to_owned(), puller: puller_name.to_owned() })
+  }
+
+  fn spawn_creep(&mut self, spawn_id: RawObjectId, body: &[Part], creep_name: &str) -> ReturnCode {
+    self.submit(Intent::SpawnCreep { spawn: spawn_id, name: creep_name.to_owned(), body: body.to_vec() })
+  }
+
+  fn create_construction_site(&mut self, pos: Position, structure_type: StructureType) -> ReturnCode {
+    self.submit(Intent::CreateConstructionSite { pos, structure_type })
+  }
+
+  fn link_transfer_energy(&mut self, link_id: RawObjectId, target_id: RawObjectId, amount: Option<u32>) -> ReturnCode {
+    self.submit(Intent::TransferEnergy { link: link_id, target: target_id, amount })
+  }
+}
@@ -0,0 +1,903 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+
+use screeps::{Direction, Part, Position, RawObjectId, ResourceType, ReturnCode, RoomName, StructureType, Terrain};
+use screeps::constants::{BUILD_POWER, CARRY_CAPACITY, CONTAINER_CAPACITY, CREEP_LIFE_TIME, CREEP_SPAWN_TIME, ENERGY_DECAY, HARVEST_POWER, LINK_CAPACITY, LINK_LOSS_RATIO, SPAWN_ENERGY_START};
+
+use super::{GameWorld, Intent, WorldSnapshot, char_terrain, RoomState, CreepState, SourceState, StructureState, ConstructionSiteState, ResourceState, direction_towards, new_position, position_xy, step};
+use crate::constants::{MAX_ACTIVE_SEGMENTS, ROOM_SIZE};
+
+const ALL_DIRECTIONS: [Direction; 8] = [Direction::Top, Direction::TopRight, Direction::Right, Direction::BottomRight, Direction::Bottom, Direction::BottomLeft, Direction::Left, Direction::TopLeft];
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WorldStats {
+  pub energy_harvested: u32,
+  pub energy_delivered: u32, // Transferred into spawns and extensions
+  pub energy_decayed: u32,
+  pub creeps_spawned: u32,
+  pub creeps_died: u32,
+}
+
+#[derive(Debug, Clone)]
+struct SpawnOrder {
+  spawn_id: RawObjectId,
+  creep_name: String,
+  remaining: u32,
+}
+
+// In-memory model of the parts of the game the hive uses. Intents are validated when issued and resolved together in
+// `end_tick`, the same way the Screeps server applies intents after the script has finished running.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryWorld {
+  pub time: u32,
+  pub raw_memory: String,
+
+  pub rooms: Vec<RoomName>,
+  pub creeps: HashMap<String,CreepState>,
+  pub sources: HashMap<RawObjectId,SourceState>,
+  pub structures: HashMap<RawObjectId,StructureState>,
+  pub resources: HashMap<RawObjectId,ResourceState>,
+  pub construction_sites: HashMap<RawObjectId,ConstructionSiteState>,
+  pub terrain: HashMap<RoomName,Vec<Terrain>>, // Row major, ROOM_SIZE.0 * ROOM_SIZE.1 tiles
+
+  pub segments: HashMap<u8,String>,
+  pub active_segments: HashSet<u8>, // Readable this tick
+
+  pub stats: WorldStats,
+  pub notifications: Vec<String>,
+
+  next_id: u128,
+  intents: Vec<Intent>,
+  spawn_orders: Vec<SpawnOrder>,
+  requested_segments: Option<Vec<u8>>,
+  written_segments: HashSet<u8>, // This tick
+}
+
+impl MemoryWorld {
+  pub fn new() -> Self {
+    Self {
+      time: 1,
+      ..Default::default()
+    }
+  }
+
+  pub fn from_snapshot(snapshot: &WorldSnapshot) -> Self {
+    let mut world = Self::new();
+    world.time = snapshot.time;
+    for room in snapshot.rooms.iter() {
+      world.add_room(room.name);
+    }
+    for room_terrain in snapshot.terrain.iter() {
+      world.set_room_terrain(room_terrain.room_name, &room_terrain.rows);
+    }
+    for creep in snapshot.creeps.iter() {
+      world.add_room(creep.pos.room_name());
+      world.creeps.insert(creep.name.to_owned(), creep.to_owned());
+    }
+    for source in snapshot.sources.iter() {
+      world.add_room(source.pos.room_name());
+      world.sources.insert(source.id, source.to_owned());
+    }
+    for structure in snapshot.structures.iter() {
+      world.add_room(structure.pos.room_name());
+      world.structures.insert(structure.id, structure.to_owned());
+    }
+    for resource in snapshot.resources.iter() {
+      world.add_room(resource.pos.room_name());
+      world.resources.insert(resource.id, resource.to_owned());
+    }
+    for site in snapshot.construction_sites.iter() {
+      world.add_room(site.pos.room_name());
+      world.construction_sites.insert(site.id, site.to_owned());
+    }
+    world
+  }
+
+  pub fn generate_id(&mut self) -> RawObjectId {
+    loop {
+      self.next_id += 1;
+      let id: RawObjectId = format!("{:024x}", self.next_id).parse().expect("generated object ids are valid hex");
+      let in_use = self.sources.contains_key(&id) || self.structures.contains_key(&id) || self.resources.contains_key(&id) || self.construction_sites.contains_key(&id) || self.creep_by_id(id).is_some();
+      if !in_use {
+        return id;
+      }
+    }
+  }
+
+  pub fn add_room(&mut self, room_name: RoomName) {
+    if !self.rooms.contains(&room_name) {
+      self.rooms.push(room_name);
+      self.terrain.insert(room_name, vec![Terrain::Plain; (ROOM_SIZE.0 * ROOM_SIZE.1) as usize]);
+    }
+  }
+
+  // One string per row, as scenarios and snapshots store it
+  pub fn set_room_terrain(&mut self, room_name: RoomName, rows: &[String]) {
+    self.add_room(room_name);
+    if let Some(tiles) = self.terrain.get_mut(&room_name) {
+      for (y, row) in rows.iter().take(ROOM_SIZE.1 as usize).enumerate() {
+        for (x, tile) in row.chars().take(ROOM_SIZE.0 as usize).enumerate() {
+          tiles[y * ROOM_SIZE.0 as usize + x] = char_terrain(tile);
+        }
+      }
+    }
+  }
+
+  pub fn set_terrain(&mut self, pos: Position, terrain: Terrain) {
+    self.add_room(pos.room_name());
+    let (x, y) = position_xy(pos);
+    if let Some(tiles) = self.terrain.get_mut(&pos.room_name()) {
+      tiles[y as usize * ROOM_SIZE.0 as usize + x as usize] = terrain;
+    }
+  }
+
+  pub fn add_source(&mut self, pos: Position, energy_capacity: u32) -> RawObjectId {
+    let id = self.generate_id();
+    self.insert_source(id, pos, energy_capacity);
+    id
+  }
+
+  pub fn insert_source(&mut self, id: RawObjectId, pos: Position, energy_capacity: u32) {
+    self.add_room(pos.room_name());
+    self.sources.insert(id, SourceState {
+      id,
+      pos,
+      energy: energy_capacity,
+      energy_capacity,
+      ticks_to_regeneration: None,
+    });
+  }
+
+  pub fn add_structure(&mut self, structure_type: StructureType, pos: Position, energy: u32, energy_capacity: u32) -> RawObjectId {
+    let id = self.generate_id();
+    self.insert_structure(id, structure_type, pos, energy, energy_capacity);
+    id
+  }
+
+  pub fn insert_structure(&mut self, id: RawObjectId, structure_type: StructureType, pos: Position, energy: u32, energy_capacity: u32) {
+    self.add_room(pos.room_name());
+    self.structures.insert(id, StructureState {
+      id,
+      structure_type,
+      pos,
+      energy,
+      energy_capacity,
+      spawning: false,
+    });
+  }
+
+  pub fn add_construction_site(&mut self, structure_type: StructureType, pos: Position) -> Option<RawObjectId> {
+    let progress_total = structure_type.construction_cost()?;
+    self.add_room(pos.room_name());
+    let id = self.generate_id();
+    self.construction_sites.insert(id, ConstructionSiteState {
+      id,
+      structure_type,
+      pos,
+      progress: 0,
+      progress_total,
+    });
+    Some(id)
+  }
+
+  pub fn add_creep(&mut self, creep_name: &str, pos: Position, body: Vec<Part>) -> RawObjectId {
+    let id = self.generate_id();
+    self.insert_creep(id, creep_name, pos, body);
+    id
+  }
+
+  pub fn insert_creep(&mut self, id: RawObjectId, creep_name: &str, pos: Position, body: Vec<Part>) {
+    self.add_room(pos.room_name());
+    let energy_capacity = body.iter().filter(|part| **part == Part::Carry).count() as u32 * CARRY_CAPACITY;
+    self.creeps.insert(creep_name.to_owned(), CreepState {
+      name: creep_name.to_owned(),
+      id: Some(id),
+      pos,
+      body,
+      spawning: false,
+      ticks_to_live: Some(CREEP_LIFE_TIME),
+      fatigue: 0,
+      energy: 0,
+      energy_capacity,
+    });
+  }
+
+  // Energy dropped on a container goes into it, only what does not fit ends up on the ground
+  pub fn drop_energy(&mut self, pos: Position, mut amount: u32) {
+    if let Some(container) = self.structures.values_mut().find(|structure| structure.pos == pos && structure.structure_type == StructureType::Container) {
+      let stored = amount.min(container.free_capacity());
+      container.energy += stored;
+      amount -= stored;
+    }
+    if amount == 0 {
+      return;
+    }
+    let existing = self.resources.values_mut().find(|resource| resource.pos == pos && resource.resource_type == ResourceType::Energy);
+    match existing {
+      Some(resource) => resource.amount += amount,
+      None => {
+        let id = self.generate_id();
+        self.resources.insert(id, ResourceState {
+          id,
+          pos,
+          resource_type: ResourceType::Energy,
+          amount,
+        });
+      },
+    }
+  }
+
+  // Only the structures the hive builds are modelled
+  fn creep_by_id(&self, id: RawObjectId) -> Option<&CreepState> {
+    self.creeps.values().find(|creep| creep.id == Some(id))
+  }
+
+  fn attack_target_pos(&self, id: RawObjectId) -> Option<Position> {
+    self.structures.get(&id).map(|structure| structure.pos)
+      .or_else(|| self.creep_by_id(id).map(|creep| creep.pos))
+  }
+
+  fn submit(&mut self, intent: Intent) -> ReturnCode {
+    let return_code = self.validate(&intent);
+    if return_code == ReturnCode::Ok {
+      self.intents.push(intent);
+    }
+    return_code
+  }
+
+  fn occupied(&self, pos: Position) -> bool {
+    self.creeps.values().any(|creep| creep.pos == pos)
+  }
+
+  fn walkable(&self, pos: Position) -> bool {
+    self.terrain(pos) != Terrain::Wall && !self.structures.values().any(|structure| {
+      structure.pos == pos && !matches!(structure.structure_type, StructureType::Road | StructureType::Container | StructureType::Rampart)
+    })
+  }
+
+  fn move_cost(&self, pos: Position) -> u32 {
+    if self.structures.values().any(|structure| structure.pos == pos && structure.structure_type == StructureType::Road) {
+      return 1;
+    }
+    match self.terrain(pos) {
+      Terrain::Swamp => 10,
+      _ => 2,
+    }
+  }
+
+  // Breadth first search over walkable tiles, the target itself is always allowed so obstacles can be approached
+  fn path_step(&self, from: Position, to: Position) -> Option<Direction> {
+    if from.room_name() != to.room_name() || from == to {
+      return None;
+    }
+    let index = |pos: Position| {
+      let (x, y) = position_xy(pos);
+      y as usize * ROOM_SIZE.0 as usize + x as usize
+    };
+    let mut first_steps: Vec<Option<Direction>> = vec![None; (ROOM_SIZE.0 * ROOM_SIZE.1) as usize];
+    let mut visited = vec![false; (ROOM_SIZE.0 * ROOM_SIZE.1) as usize];
+    let mut queue = VecDeque::new();
+    visited[index(from)] = true;
+    queue.push_back(from);
+    while let Some(pos) = queue.pop_front() {
+      for direction in ALL_DIRECTIONS {
+        let next = match step(pos, direction) {
+          Some(next) => next,
+          None => continue,
+        };
+        if visited[index(next)] || (next != to && !self.walkable(next)) {
+          continue;
+        }
+        visited[index(next)] = true;
+        first_steps[index(next)] = if pos == from { Some(direction) } else { first_steps[index(pos)] };
+        if next == to {
+          return first_steps[index(next)];
+        }
+        queue.push_back(next);
+      }
+    }
+    direction_towards(from, to)
+  }
+
+  // Every part except MOVE weighs the creep down, CARRY parts only while they hold something
+  fn creep_weight(creep: &CreepState) -> u32 {
+    let loaded_carry = (creep.energy + CARRY_CAPACITY - 1) / CARRY_CAPACITY;
+    let other = creep.body.iter().filter(|part| **part != Part::Move && **part != Part::Carry).count() as u32;
+    other + loaded_carry.min(creep.count_parts(Part::Carry))
+  }
+
+  // Resolves every intent issued this tick, applies the passive effects of a game tick and advances the clock
+  pub fn end_tick(&mut self) {
+    let intents = std::mem::take(&mut self.intents);
+
+    self.resolve_work(&intents);
+    self.resolve_movement(&intents);
+    self.resolve_spawning();
+    self.regenerate();
+
+    if let Some(requested_segments) = self.requested_segments.take() {
+      self.active_segments = requested_segments.into_iter().collect();
+    }
+    self.written_segments.clear();
+
+    for creep in self.creeps.values_mut() {
+      if !creep.spawning {
+        creep.ticks_to_live = creep.ticks_to_live.map(|ttl| ttl.saturating_sub(1));
+        creep.fatigue = creep.fatigue.saturating_sub(2 * creep.count_parts(Part::Move));
+      }
+    }
+    let creep_count = self.creeps.len();
+    let mut tombstone_energy = vec![];
+    self.creeps.retain(|_, creep| {
+      if creep.ticks_to_live == Some(0) && creep.energy > 0 {
+        tombstone_energy.push((creep.pos, creep.energy));
+      }
+      creep.ticks_to_live != Some(0)
+    });
+    self.stats.creeps_died += (creep_count - self.creeps.len()) as u32;
+    for (pos, amount) in tombstone_energy {
+      self.drop_energy(pos, amount);
+    }
+
+    self.time += 1;
+  }
+
+  fn regenerate(&mut self) {
+    for source in self.sources.values_mut() {
+      source.ticks_to_regeneration = match source.ticks_to_regeneration {
+        Some(ticks) if ticks <= 1 => {
+          source.energy = source.energy_capacity;
+          None
+        },
+        Some(ticks) => Some(ticks - 1),
+        None => None,
+      };
+    }
+
+    for room_name in self.rooms.to_owned() {
+      if self.room(room_name).map(|room| room.energy_available).unwrap_or(0) < SPAWN_ENERGY_START {
+        for structure in self.structures.values_mut() {
+          if structure.pos.room_name() == room_name && structure.structure_type == StructureType::Spawn && structure.energy < structure.energy_capacity {
+            structure.energy += 1;
+          }
+        }
+      }
+    }
+
+    for resource in self.resources.values_mut() {
+      let decayed = ((resource.amount + ENERGY_DECAY - 1) / ENERGY_DECAY).min(resource.amount);
+      resource.amount -= decayed;
+      self.stats.energy_decayed += decayed;
+    }
+    self.resources.retain(|_, resource| resource.amount > 0);
+  }
+
+  fn resolve_work(&mut self, intents: &[Intent]) {
+    for intent in intents {
+      match intent {
+        Intent::Harvest { creep: creep_name, source: source_id } => {
+          let work = match self.creeps.get(creep_name) {
+            Some(creep) => creep.count_parts(Part::Work),
+            None => continue,
+          };
+          let harvested = match self.sources.get_mut(source_id) {
+            Some(source) => {
+              let harvested = (work * HARVEST_POWER).min(source.energy);
+              source.energy -= harvested;
+              if source.ticks_to_regeneration.is_none() {
+                source.ticks_to_regeneration = Some(screeps::constants::ENERGY_REGEN_TIME);
+              }
+              harvested
+            },
+            None => continue,
+          };
+          self.stats.energy_harvested += harvested;
+          let creep = self.creeps.get_mut(creep_name).unwrap();
+          let stored = harvested.min(creep.free_capacity());
+          creep.energy += stored;
+          if harvested > stored {
+            let pos = creep.pos;
+            self.drop_energy(pos, harvested - stored);
+          }
+        },
+        Intent::Pickup { creep: creep_name, resource: resource_id } => {
+          let free_capacity = match self.creeps.get(creep_name) {
+            Some(creep) => creep.free_capacity(),
+            None => continue,
+          };
+          let picked_up = match self.resources.get_mut(resource_id) {
+            Some(resource) => {
+              let picked_up = free_capacity.min(resource.amount);
+              resource.amount -= picked_up;
+              picked_up
+            },
+            None => continue,
+          };
+          self.creeps.get_mut(creep_name).unwrap().energy += picked_up;
+        },
+        Intent::Transfer { creep: creep_name, target: target_id, amount, .. } => {
+          let available = match self.creeps.get(creep_name) {
+            Some(creep) => amount.unwrap_or(creep.energy).min(creep.energy),
+            None => continue,
+          };
+          let transferred = if let Some(structure) = self.structures.get_mut(target_id) {
+            let transferred = available.min(structure.free_capacity());
+            structure.energy += transferred;
+            if structure.structure_type == StructureType::Spawn || structure.structure_type == StructureType::Extension {
+              self.stats.energy_delivered += transferred;
+            }
+            transferred
+          } else if let Some(target_name) = self.creep_by_id(*target_id).map(|creep| creep.name.to_owned()) {
+            let target = self.creeps.get_mut(&target_name).unwrap();
+            let transferred = available.min(target.free_capacity());
+            target.energy += transferred;
+            transferred
+          } else {
+            continue
+          };
+          self.creeps.get_mut(creep_name).unwrap().energy -= transferred;
+        },
+        Intent::Withdraw { creep: creep_name, target: target_id, amount, .. } => {
+          let free_capacity = match self.creeps.get(creep_name) {
+            Some(creep) => creep.free_capacity(),
+            None => continue,
+          };
+          let withdrawn = match self.structures.get_mut(target_id) {
+            Some(structure) => {
+              let withdrawn = amount.unwrap_or(structure.energy).min(structure.energy).min(free_capacity);
+              structure.energy -= withdrawn;
+              withdrawn
+            },
+            None => continue,
+          };
+          self.creeps.get_mut(creep_name).unwrap().energy += withdrawn;
+        },
+        Intent::Build { creep: creep_name, site: site_id } => {
+          let (work, energy) = match self.creeps.get(creep_name) {
+            Some(creep) => (creep.count_parts(Part::Work), creep.energy),
+            None => continue,
+          };
+          let (built, finished) = match self.construction_sites.get_mut(site_id) {
+            Some(site) => {
+              let built = (work * BUILD_POWER).min(energy).min(site.progress_total - site.progress);
+              site.progress += built;
+              (built, site.progress >= site.progress_total)
+            },
+            None => continue,
+          };
+          self.creeps.get_mut(creep_name).unwrap().energy -= built;
+          // The finished structure keeps the site's id
+          if finished {
+            let site = self.construction_sites.remove(site_id).unwrap();
+            let energy_capacity = match site.structure_type {
+              StructureType::Container => CONTAINER_CAPACITY,
+              StructureType::Link => LINK_CAPACITY,
+              _ => 0,
+            };
+            self.insert_structure(site.id, site.structure_type, site.pos, 0, energy_capacity);
+          }
+        },
+        Intent::CreateConstructionSite { pos, structure_type } => {
+          if !self.construction_sites.values().any(|site| site.pos == *pos) {
+            self.add_construction_site(*structure_type, *pos);
+          }
+        },
+        // Link cooldowns are not modelled
+        Intent::TransferEnergy { link: link_id, target: target_id, amount } => {
+          let free_capacity = match self.structures.get(target_id) {
+            Some(target) => target.free_capacity(),
+            None => continue,
+          };
+          let sent = match self.structures.get_mut(link_id) {
+            Some(link) => {
+              let sent = amount.unwrap_or(link.energy).min(link.energy).min(free_capacity);
+              link.energy -= sent;
+              sent
+            },
+            None => continue,
+          };
+          self.structures.get_mut(target_id).unwrap().energy += sent - (sent as f32 * LINK_LOSS_RATIO).ceil() as u32;
+        },
+        Intent::Drop { creep: creep_name, amount, .. } => {
+          let (pos, dropped) = match self.creeps.get_mut(creep_name) {
+            Some(creep) => {
+              let dropped = amount.unwrap_or(creep.energy).min(creep.energy);
+              creep.energy -= dropped;
+              (creep.pos, dropped)
+            },
+            None => continue,
+          };
+          if dropped > 0 {
+            self.drop_energy(pos, dropped);
+          }
+        },
+        _ => {},
+      }
+    }
+    self.resources.retain(|_, resource| resource.amount > 0);
+  }
+
+  fn resolve_movement(&mut self, intents: &[Intent]) {
+    let pulled_by = |creep_name: &str, puller_name: &str| intents.iter().any(|intent| matches!(intent, Intent::Pull { creep, pulled } if creep == puller_name && pulled == creep_name));
+    let mut destinations: Vec<(String,Position)> = vec![];
+    for intent in intents {
+      let creep_name = intent.actor();
+      let creep = match self.creeps.get(&creep_name) {
+        Some(creep) => creep,
+        None => continue,
+      };
+      let direction = match intent {
+        Intent::MoveDirection { direction, .. } => Some(*direction),
+        Intent::MoveTo { target, .. } => self.path_step(creep.pos, *target),
+        Intent::MovePulledBy { puller: puller_name, .. } => {
+          if pulled_by(&creep_name, puller_name) || creep.count_parts(Part::Move) > 0 {
+            self.creeps.get(puller_name).and_then(|puller| direction_towards(creep.pos, puller.pos))
+          } else {
+            None
+          }
+        },
+        _ => None,
+      };
+      if let Some(destination) = direction.and_then(|direction| step(creep.pos, direction)).filter(|destination| self.walkable(*destination)) {
+        destinations.retain(|(name, _)| *name != creep_name);
+        destinations.push((creep_name, destination));
+      }
+    }
+
+    // Drop moves into tiles that stay occupied until nothing changes, which leaves chains and swaps intact
+    loop {
+      let blocked = destinations.iter().position(|(creep_name, destination)| {
+        let contested = destinations.iter().any(|(other_name, other_destination)| other_name != creep_name && other_destination == destination);
+        let stays_occupied = self.creeps.values().any(|other| other.name != *creep_name && other.pos == *destination && !destinations.iter().any(|(moving_name, _)| *moving_name == other.name));
+        contested || stays_occupied
+      });
+      match blocked {
+        Some(index) => { destinations.remove(index); },
+        None => break,
+      }
+    }
+
+    for (creep_name, destination) in destinations {
+      let fatigue = match self.creeps.get(&creep_name) {
+        Some(creep) => Self::creep_weight(creep) * self.move_cost(destination),
+        None => continue,
+      };
+      // A pulled creep hands its fatigue to whoever is pulling it
+      let puller = intents.iter().find_map(|intent| match intent {
+        Intent::MovePulledBy { creep, puller } if *creep == creep_name && pulled_by(&creep_name, puller) => Some(puller.to_owned()),
+        _ => None,
+      });
+      let fatigued_name = puller.unwrap_or_else(|| creep_name.to_owned());
+      if let Some(fatigued) = self.creeps.get_mut(&fatigued_name) {
+        fatigued.fatigue += fatigue;
+      }
+      if let Some(creep) = self.creeps.get_mut(&creep_name) {
+        creep.pos = destination;
+      }
+    }
+  }
+
+  fn resolve_spawning(&mut self) {
+    for order in self.spawn_orders.iter_mut() {
+      order.remaining = order.remaining.saturating_sub(1);
+    }
+
+    let (finished, in_progress): (Vec<_>, Vec<_>) = std::mem::take(&mut self.spawn_orders).into_iter().partition(|order| order.remaining == 0);
+    self.spawn_orders = in_progress;
+
+    for order in finished {
+      let spawn_pos = match self.structures.get_mut(&order.spawn_id) {
+        Some(spawn) => {
+          spawn.spawning = false;
+          spawn.pos
+        },
+        None => continue,
+      };
+      let exit = ALL_DIRECTIONS
+        .into_iter()
+        .filter_map(|direction| step(spawn_pos, direction))
+        .find(|pos| self.walkable(*pos) && !self.occupied(*pos));
+      if let Some(creep) = self.creeps.get_mut(&order.creep_name) {
+        creep.spawning = false;
+        creep.pos = exit.unwrap_or(spawn_pos);
+      }
+    }
+  }
+
+  fn withdraw_room_energy(&mut self, room_name: RoomName, mut amount: u32) {
+    let mut structures = self.structures.values_mut()
+      .filter(|structure| structure.pos.room_name() == room_name && (structure.structure_type == StructureType::Spawn || structure.structure_type == StructureType::Extension))
+      .collect::<Vec<_>>();
+    structures.sort_by_key(|structure| structure.structure_type != StructureType::Spawn);
+    for structure in structures {
+      let withdrawn = amount.min(structure.energy);
+      structure.energy -= withdrawn;
+      amount -= withdrawn;
+    }
+  }
+}
+
+impl GameWorld for MemoryWorld {
+  fn time(&self) -> u32 {
+    self.time
+  }
+
+  fn raw_memory(&self) -> String {
+    self.raw_memory.to_owned()
+  }
+
+  fn set_raw_memory(&mut self, serialized: &str) {
+    self.raw_memory = serialized.to_owned();
+  }
+
+  fn room_names(&self) -> Vec<RoomName> {
+    self.rooms.to_owned()
+  }
+
+  fn room(&self, room_name: RoomName) -> Option<RoomState> {
+    if !self.rooms.contains(&room_name) {
+      return None;
+    }
+    let (energy_available, energy_capacity_available) = self.structures.values()
+      .filter(|structure| structure.pos.room_name() == room_name && (structure.structure_type == StructureType::Spawn || structure.structure_type == StructureType::Extension))
+      .fold((0,0), |totals, structure| (totals.0 + structure.energy, totals.1 + structure.energy_capacity));
+    Some(RoomState {
+      name: room_name,
+      energy_available,
+      energy_capacity_available,
+    })
+  }
+
+  fn creep(&self, creep_name: &str) -> Option<CreepState> {
+    self.creeps.get(creep_name).cloned()
+  }
+
+  fn creep_names(&self) -> Vec<String> {
+    self.creeps.keys().cloned().collect()
+  }
+
+  fn source(&self, source_id: RawObjectId) -> Option<SourceState> {
+    self.sources.get(&source_id).cloned()
+  }
+
+  fn find_sources(&self, room_name: RoomName) -> Vec<SourceState> {
+    self.sources.values().filter(|source| source.pos.room_name() == room_name).cloned().collect()
+  }
+
+  fn structure(&self, structure_id: RawObjectId) -> Option<StructureState> {
+    self.structures.get(&structure_id).cloned()
+  }
+
+  fn find_my_structures(&self, room_name: RoomName) -> Vec<StructureState> {
+    self.structures.values()
+      .filter(|structure| structure.pos.room_name() == room_name && !matches!(structure.structure_type, StructureType::Road | StructureType::Container))
+      .cloned()
+      .collect()
+  }
+
+  fn find_structures(&self, room_name: RoomName) -> Vec<StructureState> {
+    self.structures.values().filter(|structure| structure.pos.room_name() == room_name).cloned().collect()
+  }
+
+  fn find_construction_sites(&self, room_name: RoomName) -> Vec<ConstructionSiteState> {
+    self.construction_sites.values().filter(|site| site.pos.room_name() == room_name).cloned().collect()
+  }
+
+  fn terrain(&self, pos: Position) -> Terrain {
+    let (x, y) = position_xy(pos);
+    self.terrain.get(&pos.room_name())
+      .map(|tiles| tiles[y as usize * ROOM_SIZE.0 as usize + x as usize])
+      .unwrap_or(Terrain::Wall)
+  }
+
+  fn room_terrain(&self, room_name: RoomName) -> Vec<Terrain> {
+    self.terrain.get(&room_name)
+      .cloned()
+      .unwrap_or_else(|| vec![Terrain::Wall; (ROOM_SIZE.0 * ROOM_SIZE.1) as usize])
+  }
+
+  fn object_pos(&self, id: RawObjectId) -> Option<Position> {
+    self.attack_target_pos(id)
+      .or_else(|| self.sources.get(&id).map(|source| source.pos))
+      .or_else(|| self.resources.get(&id).map(|resource| resource.pos))
+      .or_else(|| self.construction_sites.get(&id).map(|site| site.pos))
+  }
+
+  // Paths never leave the room, rooms are not connected to each other here. Cheapest by `move_cost`, the same costs
+  // ScreepsWorld gives the game's PathFinder, so only paths that cost exactly the same can come out differently.
+  fn find_path(&self, from: Position, to: Position, range: u32) -> Option<Vec<Position>> {
+    if from.room_name() != to.room_name() {
+      return None;
+    }
+    if from.get_range_to(&to) <= range {
+      return Some(vec![]);
+    }
+    let index = |pos: Position| {
+      let (x, y) = position_xy(pos);
+      y as usize * ROOM_SIZE.0 as usize + x as usize
+    };
+    let mut parents: Vec<Option<Position>> = vec![None; (ROOM_SIZE.0 * ROOM_SIZE.1) as usize];
+    let mut costs = vec![u32::MAX; (ROOM_SIZE.0 * ROOM_SIZE.1) as usize];
+    let mut open = BinaryHeap::new();
+    costs[index(from)] = 0;
+    open.push(Reverse((0, position_xy(from))));
+    while let Some(Reverse((cost, (x, y)))) = open.pop() {
+      let pos = new_position(x, y, from.room_name());
+      if cost > costs[index(pos)] {
+        continue;
+      }
+      if pos.get_range_to(&to) <= range {
+        let mut path = vec![];
+        let mut current = pos;
+        while current != from {
+          path.push(current);
+          current = match parents[index(current)] {
+            Some(parent) => parent,
+            None => break,
+          };
+        }
+        path.reverse();
+        return Some(path);
+      }
+      for direction in ALL_DIRECTIONS {
+        let next = match step(pos, direction) {
+          Some(next) => next,
+          None => continue,
+        };
+        if next != to && !self.walkable(next) {
+          continue;
+        }
+        let next_cost = cost + self.move_cost(next);
+        if next_cost < costs[index(next)] {
+          costs[index(next)] = next_cost;
+          parents[index(next)] = Some(pos);
+          open.push(Reverse((next_cost, position_xy(next))));
+        }
+      }
+    }
+    None
+  }
+
+  fn look_for_energy(&self, pos: Position) -> Vec<ResourceState> {
+    self.resources.values().filter(|resource| resource.pos == pos && resource.resource_type == ResourceType::Energy).cloned().collect()
+  }
+
+  fn find_dropped_resources(&self, room_name: RoomName) -> Vec<ResourceState> {
+    self.resources.values().filter(|resource| resource.pos.room_name() == room_name).cloned().collect()
+  }
+
+  fn segment(&self, segment_id: u8) -> Option<String> {
+    if !self.active_segments.contains(&segment_id) {
+      return None;
+    }
+    Some(self.segments.get(&segment_id).cloned().unwrap_or_default())
+  }
+
+  // Writes past the per tick limit are dropped, the game refuses to save any of them
+  fn set_segment(&mut self, segment_id: u8, data: &str) {
+    if !self.written_segments.contains(&segment_id) && self.segment_writes_left() == 0 {
+      return;
+    }
+    self.written_segments.insert(segment_id);
+    self.segments.insert(segment_id, data.to_owned());
+  }
+
+  fn segment_writes_left(&self) -> usize {
+    MAX_ACTIVE_SEGMENTS.saturating_sub(self.written_segments.len())
+  }
+
+  // Like the game, requested segments become readable on the next tick and stay readable until replaced
+  fn set_active_segments(&mut self, segment_ids: &[u8]) {
+    self.requested_segments = Some(segment_ids.to_vec());
+  }
+
+  fn notify(&mut self, message: &str) {
+    self.notifications.push(message.to_owned());
+  }
+
+  fn validate(&self, intent: &Intent) -> ReturnCode {
+    intent.check(self)
+  }
+
+  fn creep_move_direction(&mut self, creep_name: &str, direction: Direction) -> ReturnCode {
+    self.submit(Intent::MoveDirection { creep: creep_name.to_owned(), direction })
+  }
+
+  fn creep_move_to(&mut self, creep_name: &str, target: Position) -> ReturnCode {
+    self.submit(Intent::MoveTo { creep: creep_name.to_owned(), target })
+  }
+
+  fn creep_harvest(&mut self, creep_name: &str, source_id: RawObjectId) -> ReturnCode {
+    self.submit(Intent::Harvest { creep: creep_name.to_owned(), source: source_id })
+  }
+
+  fn creep_pickup(&mut self, creep_name: &str, resource_id: RawObjectId) -> ReturnCode {
+    self.submit(Intent::Pickup { creep: creep_name.to_owned(), resource: resource_id })
+  }
+
+  fn creep_transfer(&mut self, creep_name: &str, target_id: RawObjectId, resource_type: ResourceType, amount: Option<u32>) -> ReturnCode {
+    self.submit(Intent::Transfer { creep: creep_name.to_owned(), target: target_id, resource_type, amount })
+  }
+
+  fn creep_withdraw(&mut self, creep_name: &str, target_id: RawObjectId, resource_type: ResourceType, amount: Option<u32>) -> ReturnCode {
+    self.submit(Intent::Withdraw { creep: creep_name.to_owned(), target: target_id, resource_type, amount })
+  }
+
+  fn creep_drop(&mut self, creep_name: &str, resource_type: ResourceType, amount: Option<u32>) -> ReturnCode {
+    self.submit(Intent::Drop { creep: creep_name.to_owned(), resource_type, amount })
+  }
+
+  fn creep_build(&mut self, creep_name: &str, site_id: RawObjectId) -> ReturnCode {
+    self.submit(Intent::Build { creep: creep_name.to_owned(), site: site_id })
+  }
+
+  fn creep_repair(&mut self, creep_name: &str, target_id: RawObjectId) -> ReturnCode {
+    self.submit(Intent::Repair { creep: creep_name.to_owned(), target: target_id })
+  }
+
+  fn creep_upgrade_controller(&mut self, creep_name: &str, controller_id: RawObjectId) -> ReturnCode {
+    self.submit(Intent::UpgradeController { creep: creep_name.to_owned(), controller: controller_id })
+  }
+
+  fn creep_dismantle(&mut self, creep_name: &str, target_id: RawObjectId) -> ReturnCode {
+    self.submit(Intent::Dismantle { creep: creep_name.to_owned(), target: target_id })
+  }
+
+  fn creep_claim_controller(&mut self, creep_name: &str, controller_id: RawObjectId) -> ReturnCode {
+    self.submit(Intent::ClaimController { creep: creep_name.to_owned(), controller: controller_id })
+  }
+
+  fn creep_reserve_controller(&mut self, creep_name: &str, controller_id: RawObjectId) -> ReturnCode {
+    self.submit(Intent::ReserveController { creep: creep_name.to_owned(), controller: controller_id })
+  }
+
+  fn creep_heal(&mut self, creep_name: &str, target_id: RawObjectId) -> ReturnCode {
+    self.submit(Intent::Heal { creep: creep_name.to_owned(), target: target_id })
+  }
+
+  fn creep_attack(&mut self, creep_name: &str, target_id: RawObjectId) -> ReturnCode {
+    self.submit(Intent::Attack { creep: creep_name.to_owned(), target: target_id })
+  }
+
+  fn creep_ranged_attack(&mut self, creep_name: &str, target_id: RawObjectId) -> ReturnCode {
+    self.submit(Intent::RangedAttack { creep: creep_name.to_owned(), target: target_id })
+  }
+
+  fn creep_pull(&mut self, creep_name: &str, pulled_name: &str) -> ReturnCode {
+    self.submit(Intent::Pull { creep: creep_name.to_owned(), pulled: pulled_name.to_owned() })
+  }
+
+  fn creep_move_pulled_by(&mut self, creep_name: &str, puller_name: &str) -> ReturnCode {
+    self.submit(Intent::MovePulledBy { creep: creep_name.to_owned(), puller: puller_name.to_owned() })
+  }
+
+  // Spawning starts straight away rather than at the end of the tick so the creep is visible to anything that runs later
+  fn spawn_creep(&mut self, spawn_id: RawObjectId, body: &[Part], creep_name: &str) -> ReturnCode {
+    let return_code = self.validate(&Intent::SpawnCreep { spawn: spawn_id, name: creep_name.to_owned(), body: body.to_vec() });
+    if return_code != ReturnCode::Ok {
+      return return_code;
+    }
+
+    let spawn_pos = self.structures[&spawn_id].pos;
+    let cost = body.iter().map(|part| part.cost()).sum::<u32>();
+    self.withdraw_room_energy(spawn_pos.room_name(), cost);
+    self.add_creep(creep_name, spawn_pos, body.to_vec());
+    self.creeps.get_mut(creep_name).unwrap().spawning = true;
+    self.structures.get_mut(&spawn_id).unwrap().spawning = true;
+    self.stats.creeps_spawned += 1;
+    self.spawn_orders.push(SpawnOrder {
+      spawn_id,
+      creep_name: creep_name.to_owned(),
+      remaining: body.len() as u32 * CREEP_SPAWN_TIME,
+    });
+    ReturnCode::Ok
+  }
+
+  fn create_construction_site(&mut self, pos: Position, structure_type: StructureType) -> ReturnCode {
+    self.submit(Intent::CreateConstructionSite { pos, structure_type })
+  }
+
+  fn link_transfer_energy(&mut self, link_id: RawObjectId, target_id: RawObjectId, amount: Option<u32>) -> ReturnCode {
+    self.submit(Intent::TransferEnergy { link: link_id, target: target_id, amount })
+  }
+}
@@ -0,0 +1,327 @@
+use screeps::{Direction, Part, Position, RawObjectId, ResourceType, ReturnCode, RoomCoordinate, RoomName, StructureType, Terrain};
+use serde::{Serialize, Deserialize};
+
+use crate::constants::ROOM_SIZE;
+
+pub mod intents;
+#[cfg(target_arch = "wasm32")]
+pub mod screeps_world;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod memory_world;
+
+pub use intents::{Intent, IntentQueue, IntentResult, IntentSlot};
+#[cfg(target_arch = "wasm32")]
+pub use screeps_world::ScreepsWorld;
+#[cfg(not(target_arch = "wasm32"))]
+pub use memory_world::MemoryWorld;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RoomState {
+  pub name: RoomName,
+  pub energy_available: u32,
+  pub energy_capacity_available: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CreepState {
+  pub name: String,
+  pub id: Option<RawObjectId>, // None while spawning
+  pub pos: Position,
+  pub body: Vec<Part>,
+  pub spawning: bool,
+  pub ticks_to_live: Option<u32>,
+  pub fatigue: u32,
+  pub energy: u32,
+  pub energy_capacity: u32,
+}
+
+impl CreepState {
+  pub fn count_parts(&self, part: Part) -> u32 {
+    self.body.iter().filter(|body_part| **body_part == part).count() as u32
+  }
+
+  pub fn free_capacity(&self) -> u32 {
+    self.energy_capacity.saturating_sub(self.energy)
+  }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SourceState {
+  pub id: RawObjectId,
+  pub pos: Position,
+  pub energy: u32,
+  pub energy_capacity: u32,
+  pub ticks_to_regeneration: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StructureState {
+  pub id: RawObjectId,
+  pub structure_type: StructureType,
+  pub pos: Position,
+  pub energy: u32,
+  pub energy_capacity: u32,
+  pub spawning: bool, // Only meaningful for spawns
+}
+
+impl StructureState {
+  pub fn free_capacity(&self) -> u32 {
+    self.energy_capacity.saturating_sub(self.energy)
+  }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ConstructionSiteState {
+  pub id: RawObjectId,
+  pub structure_type: StructureType,
+  pub pos: Position,
+  pub progress: u32,
+  pub progress_total: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ResourceState {
+  pub id: RawObjectId,
+  pub pos: Position,
+  pub resource_type: ResourceType,
+  pub amount: u32,
+}
+
+// Everything the hive reads from or does to the game goes through this trait so that the same logic can run against
+// the live Screeps API (ScreepsWorld) or an in-memory model on native builds (MemoryWorld).
+pub trait GameWorld {
+  fn time(&self) -> u32;
+
+  fn raw_memory(&self) -> String;
+  fn set_raw_memory(&mut self, serialized: &str);
+
+  fn room_names(&self) -> Vec<RoomName>;
+  fn room(&self, room_name: RoomName) -> Option<RoomState>;
+
+  fn creep(&self, creep_name: &str) -> Option<CreepState>;
+  fn creep_names(&self) -> Vec<String>;
+
+  fn source(&self, source_id: RawObjectId) -> Option<SourceState>;
+  fn find_sources(&self, room_name: RoomName) -> Vec<SourceState>;
+
+  fn structure(&self, structure_id: RawObjectId) -> Option<StructureState>;
+  fn find_my_structures(&self, room_name: RoomName) -> Vec<StructureState>;
+  // Also includes structures nobody owns, such as containers and roads
+  fn find_structures(&self, room_name: RoomName) -> Vec<StructureState>;
+  fn find_construction_sites(&self, room_name: RoomName) -> Vec<ConstructionSiteState>;
+
+  // Position of any visible object, None once it is gone
+  fn object_pos(&self, id: RawObjectId) -> Option<Position>;
+
+  fn terrain(&self, pos: Position) -> Terrain;
+  // Row major, ROOM_SIZE.0 * ROOM_SIZE.1 tiles
+  fn room_terrain(&self, room_name: RoomName) -> Vec<Terrain>;
+  // Positions to walk through, excluding `from` and ending within `range` of `to`. None when no complete path exists.
+  fn find_path(&self, from: Position, to: Position, range: u32) -> Option<Vec<Position>>;
+  fn look_for_energy(&self, pos: Position) -> Vec<ResourceState>;
+  fn find_dropped_resources(&self, room_name: RoomName) -> Vec<ResourceState>;
+
+  fn segment(&self, segment_id: u8) -> Option<String>;
+  fn set_segment(&mut self, segment_id: u8, data: &str);
+  // The game only accepts MAX_ACTIVE_SEGMENTS different segments being written in a tick
+  fn segment_writes_left(&self) -> usize;
+  fn set_active_segments(&mut self, segment_ids: &[u8]);
+
+  // Emails the player, for things that need a human to look at them
+  fn notify(&mut self, message: &str);
+
+  // Return code the intent is expected to produce if it were dispatched now, without side effects
+  fn validate(&self, intent: &Intent) -> ReturnCode;
+
+  fn creep_move_direction(&mut self, creep_name: &str, direction: Direction) -> ReturnCode;
+  fn creep_move_to(&mut self, creep_name: &str, target: Position) -> ReturnCode;
+  fn creep_harvest(&mut self, creep_name: &str, source_id: RawObjectId) -> ReturnCode;
+  fn creep_pickup(&mut self, creep_name: &str, resource_id: RawObjectId) -> ReturnCode;
+  fn creep_transfer(&mut self, creep_name: &str, target_id: RawObjectId, resource_type: ResourceType, amount: Option<u32>) -> ReturnCode;
+  fn creep_withdraw(&mut self, creep_name: &str, target_id: RawObjectId, resource_type: ResourceType, amount: Option<u32>) -> ReturnCode;
+  fn creep_drop(&mut self, creep_name: &str, resource_type: ResourceType, amount: Option<u32>) -> ReturnCode;
+  fn creep_build(&mut self, creep_name: &str, site_id: RawObjectId) -> ReturnCode;
+  fn creep_repair(&mut self, creep_name: &str, target_id: RawObjectId) -> ReturnCode;
+  fn creep_upgrade_controller(&mut self, creep_name: &str, controller_id: RawObjectId) -> ReturnCode;
+  fn creep_dismantle(&mut self, creep_name: &str, target_id: RawObjectId) -> ReturnCode;
+  fn creep_claim_controller(&mut self, creep_name: &str, controller_id: RawObjectId) -> ReturnCode;
+  fn creep_reserve_controller(&mut self, creep_name: &str, controller_id: RawObjectId) -> ReturnCode;
+  fn creep_heal(&mut self, creep_name: &str, target_id: RawObjectId) -> ReturnCode;
+  fn creep_attack(&mut self, creep_name: &str, target_id: RawObjectId) -> ReturnCode;
+  fn creep_ranged_attack(&mut self, creep_name: &str, target_id: RawObjectId) -> ReturnCode;
+  fn creep_pull(&mut self, creep_name: &str, pulled_name: &str) -> ReturnCode;
+  fn creep_move_pulled_by(&mut self, creep_name: &str, puller_name: &str) -> ReturnCode;
+
+  fn spawn_creep(&mut self, spawn_id: RawObjectId, body: &[Part], creep_name: &str) -> ReturnCode;
+  fn create_construction_site(&mut self, pos: Position, structure_type: StructureType) -> ReturnCode;
+  fn link_transfer_energy(&mut self, link_id: RawObjectId, target_id: RawObjectId, amount: Option<u32>) -> ReturnCode;
+}
+
+// Terrain in the same layout scenarios use, one string per row
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RoomTerrain {
+  pub room_name: RoomName,
+  pub rows: Vec<String>,
+}
+
+// Everything visible at the start of a tick, enough to rebuild a MemoryWorld that makes the same decisions
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct WorldSnapshot {
+  pub time: u32,
+  pub rooms: Vec<RoomState>,
+  pub creeps: Vec<CreepState>,
+  pub sources: Vec<SourceState>,
+  pub structures: Vec<StructureState>,
+  pub resources: Vec<ResourceState>,
+  #[serde(default)]
+  pub construction_sites: Vec<ConstructionSiteState>,
+  #[serde(default)]
+  pub terrain: Vec<RoomTerrain>,
+}
+
+impl WorldSnapshot {
+  pub fn capture(world: &dyn GameWorld) -> Self {
+    let room_names = world.room_names();
+    Self {
+      time: world.time(),
+      rooms: room_names.iter().filter_map(|room_name| world.room(*room_name)).collect(),
+      creeps: world.creep_names().iter().filter_map(|creep_name| world.creep(creep_name)).collect(),
+      sources: room_names.iter().flat_map(|room_name| world.find_sources(*room_name)).collect(),
+      structures: room_names.iter().flat_map(|room_name| world.find_structures(*room_name)).collect(),
+      resources: room_names.iter().flat_map(|room_name| world.find_dropped_resources(*room_name)).collect(),
+      construction_sites: room_names.iter().flat_map(|room_name| world.find_construction_sites(*room_name)).collect(),
+      terrain: room_names.iter().map(|room_name| RoomTerrain {
+        room_name: *room_name,
+        rows: world.room_terrain(*room_name)
+          .chunks(ROOM_SIZE.0 as usize)
+          .map(|row| row.iter().map(|terrain| terrain_char(*terrain)).collect())
+          .collect(),
+      }).collect(),
+    }
+  }
+}
+
+pub fn terrain_char(terrain: Terrain) -> char {
+  match terrain {
+    Terrain::Wall => '#',
+    Terrain::Swamp => '~',
+    Terrain::Plain => '.',
+  }
+}
+
+// Anything that is not a wall or a swamp is plain
+pub fn char_terrain(tile: char) -> Terrain {
+  match tile {
+    '#' => Terrain::Wall,
+    '~' => Terrain::Swamp,
+    _ => Terrain::Plain,
+  }
+}
+
+pub fn new_position(x: u8, y: u8, room_name: RoomName) -> Position {
+  Position::new(RoomCoordinate::new(x).unwrap(), RoomCoordinate::new(y).unwrap(), room_name)
+}
+
+pub fn position_xy(pos: Position) -> (u8,u8) {
+  (pos.x().u8(), pos.y().u8())
+}
+
+pub fn direction_offset(direction: Direction) -> (i32,i32) {
+  match direction {
+    Direction::Top => (0,-1),
+    Direction::TopRight => (1,-1),
+    Direction::Right => (1,0),
+    Direction::BottomRight => (1,1),
+    Direction::Bottom => (0,1),
+    Direction::BottomLeft => (-1,1),
+    Direction::Left => (-1,0),
+    Direction::TopLeft => (-1,-1),
+  }
+}
+
+pub fn direction_towards(from: Position, to: Position) -> Option<Direction> {
+  let (from_x, from_y) = position_xy(from);
+  let (to_x, to_y) = position_xy(to);
+  match ((to_x as i32 - from_x as i32).signum(), (to_y as i32 - from_y as i32).signum()) {
+    (0,-1) => Some(Direction::Top),
+    (1,-1) => Some(Direction::TopRight),
+    (1,0) => Some(Direction::Right),
+    (1,1) => Some(Direction::BottomRight),
+    (0,1) => Some(Direction::Bottom),
+    (-1,1) => Some(Direction::BottomLeft),
+    (-1,0) => Some(Direction::Left),
+    (-1,-1) => Some(Direction::TopLeft),
+    _ => None,
+  }
+}
+
+pub fn direction_digit(direction: Direction) -> char {
+  match direction {
+    Direction::Top => '1',
+    Direction::TopRight => '2',
+    Direction::Right => '3',
+    Direction::BottomRight => '4',
+    Direction::Bottom => '5',
+    Direction::BottomLeft => '6',
+    Direction::Left => '7',
+    Direction::TopLeft => '8',
+  }
+}
+
+pub fn digit_direction(digit: char) -> Option<Direction> {
+  match digit {
+    '1' => Some(Direction::Top),
+    '2' => Some(Direction::TopRight),
+    '3' => Some(Direction::Right),
+    '4' => Some(Direction::BottomRight),
+    '5' => Some(Direction::Bottom),
+    '6' => Some(Direction::BottomLeft),
+    '7' => Some(Direction::Left),
+    '8' => Some(Direction::TopLeft),
+    _ => None,
+  }
+}
+
+// Where a creep ends up after moving. Moving onto an exit tile carries it on to the edge of the neighbouring room.
+pub fn step_along(pos: Position, direction: Direction) -> Position {
+  let (world_x, world_y) = pos.world_coords();
+  let (dx, dy) = direction_offset(direction);
+  let next = Position::from_world_coords(world_x + dx, world_y + dy);
+  let (x, y) = position_xy(next);
+  let (width, height) = crate::constants::ROOM_SIZE;
+  let exit_x = (x == 0 && dx < 0) || (x as u32 == width - 1 && dx > 0);
+  let exit_y = (y == 0 && dy < 0) || (y as u32 == height - 1 && dy > 0);
+  Position::from_world_coords(world_x + dx * (1 + exit_x as i32), world_y + dy * (1 + exit_y as i32))
+}
+
+// Directions that walk a path found by `find_path`. Room transitions happen on their own once a creep steps onto an
+// exit tile, so the hop between rooms is skipped.
+pub fn path_directions(from: Position, path: &[Position]) -> Vec<Direction> {
+  let mut directions = vec![];
+  let mut current = from;
+  for pos in path {
+    if pos.room_name() != current.room_name() {
+      current = *pos;
+      continue;
+    }
+    match direction_towards(current, *pos) {
+      Some(direction) => directions.push(direction),
+      None => break,
+    }
+    current = *pos;
+  }
+  directions
+}
+
+// Returns None when the step would leave the room
+pub fn step(pos: Position, direction: Direction) -> Option<Position> {
+  let (x, y) = position_xy(pos);
+  let (dx, dy) = direction_offset(direction);
+  let (new_x, new_y) = (x as i32 + dx, y as i32 + dy);
+  let (width, height) = crate::constants::ROOM_SIZE;
+  if new_x < 0 || new_y < 0 || new_x >= width as i32 || new_y >= height as i32 {
+    None
+  } else {
+    Some(new_position(new_x as u8, new_y as u8, pos.room_name()))
+  }
+}
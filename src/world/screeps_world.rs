@@ -0,0 +1,496 @@
+use std::sync::{Mutex, MutexGuard};
+
+use screeps::{game, find, look, ConstructionSite, Creep, Direction, HasId, HasPosition, HasStore, JsCast, ObjectId, Part, Position, RawObjectId, ResourceType, ReturnCode, RoomName, SharedCreepProperties, Source, Structure, StructureContainer, StructureController, StructureExtension, StructureLink, StructureObject, StructureSpawn, StructureType, Terrain, Transferable, Withdrawable};
+use screeps::{LocalCostMatrix, pathfinder::{MultiRoomCostResult, SearchOptions}};
+
+use crate::constants::{MAX_ACTIVE_SEGMENTS, ROOM_SIZE};
+use super::{GameWorld, Intent, RoomState, CreepState, SourceState, StructureState, ConstructionSiteState, ResourceState};
+
+// The segment write limit covers the whole tick, console commands included, so every ScreepsWorld shares one count
+static WRITTEN_SEGMENTS: Mutex<(u32,Vec<u8>)> = Mutex::new((0, Vec::new()));
+
+#[derive(Debug, Clone, Default)]
+pub struct ScreepsWorld;
+
+impl ScreepsWorld {
+  pub fn new() -> Self {
+    Self
+  }
+
+  // Segments written so far this tick
+  fn written_segments() -> MutexGuard<'static,(u32,Vec<u8>)> {
+    let mut written = WRITTEN_SEGMENTS.lock().unwrap();
+    if written.0 != game::time() {
+      *written = (game::time(), vec![]);
+    }
+    written
+  }
+
+  // Same costs MemoryWorld moves with: roads are cheaper than plains, containers and ramparts can be walked over and
+  // every other structure blocks
+  fn room_costs(room_name: RoomName) -> MultiRoomCostResult {
+    let room = match game::rooms().get(room_name) {
+      Some(room) => room,
+      None => return MultiRoomCostResult::Default,
+    };
+    let mut costs = LocalCostMatrix::new();
+    for structure in room.find(find::STRUCTURES).iter() {
+      let cost = match structure.as_structure().structure_type() {
+        StructureType::Road => 1,
+        StructureType::Container | StructureType::Rampart => continue,
+        _ => u8::MAX,
+      };
+      costs.set(structure.pos().xy(), cost);
+    }
+    MultiRoomCostResult::CostMatrix(costs.into())
+  }
+
+  fn get_creep(creep_name: &str) -> Option<Creep> {
+    game::creeps().get(creep_name.to_owned())
+  }
+
+  fn creep_state(creep: &Creep) -> CreepState {
+    let store = creep.store();
+    CreepState {
+      name: creep.name(),
+      id: creep.try_id().map(RawObjectId::from),
+      pos: creep.pos().into(),
+      body: creep.body().iter().map(|body_part| body_part.part()).collect(),
+      spawning: creep.spawning(),
+      ticks_to_live: creep.ticks_to_live(),
+      fatigue: creep.fatigue(),
+      energy: store.get_used_capacity(Some(ResourceType::Energy)),
+      energy_capacity: store.get_capacity(Some(ResourceType::Energy)),
+    }
+  }
+
+  fn source_state(source: &Source) -> SourceState {
+    SourceState {
+      id: source.raw_id(),
+      pos: source.pos().into(),
+      energy: source.energy(),
+      energy_capacity: source.energy_capacity(),
+      ticks_to_regeneration: source.ticks_to_regeneration(),
+    }
+  }
+
+  fn structure_state(structure: &StructureObject) -> StructureState {
+    let (energy, energy_capacity, spawning) = match structure {
+      StructureObject::StructureSpawn(spawn) => (spawn.store().get_used_capacity(Some(ResourceType::Energy)), spawn.store().get_capacity(Some(ResourceType::Energy)), spawn.spawning().is_some()),
+      StructureObject::StructureExtension(extension) => (extension.store().get_used_capacity(Some(ResourceType::Energy)), extension.store().get_capacity(Some(ResourceType::Energy)), false),
+      StructureObject::StructureContainer(container) => (container.store().get_used_capacity(Some(ResourceType::Energy)), container.store().get_capacity(Some(ResourceType::Energy)), false),
+      StructureObject::StructureLink(link) => (link.store().get_used_capacity(Some(ResourceType::Energy)), link.store().get_capacity(Some(ResourceType::Energy)), false),
+      _ => (0, 0, false),
+    };
+    StructureState {
+      id: structure.as_structure().raw_id(),
+      structure_type: structure.as_structure().structure_type(),
+      pos: structure.pos().into(),
+      energy,
+      energy_capacity,
+      spawning,
+    }
+  }
+
+  fn construction_site_state(site: &ConstructionSite) -> Option<ConstructionSiteState> {
+    Some(ConstructionSiteState {
+      id: site.try_id()?.into(),
+      structure_type: site.structure_type(),
+      pos: site.pos().into(),
+      progress: site.progress(),
+      progress_total: site.progress_total(),
+    })
+  }
+
+  fn transfer_to<T: Transferable + ?Sized>(creep: &Creep, target: &T, resource_type: ResourceType, amount: Option<u32>) -> ReturnCode {
+    creep.transfer(target, resource_type, amount)
+  }
+
+  fn withdraw_from<T: Withdrawable + ?Sized>(creep: &Creep, target: &T, resource_type: ResourceType, amount: Option<u32>) -> ReturnCode {
+    creep.withdraw(target, resource_type, amount)
+  }
+
+  fn with_controller(creep_name: &str, controller_id: RawObjectId, action: impl Fn(&Creep, &StructureController) -> ReturnCode) -> ReturnCode {
+    match (Self::get_creep(creep_name), ObjectId::<StructureController>::from(controller_id).resolve()) {
+      (Some(creep), Some(controller)) => action(&creep, &controller),
+      (None, _) => ReturnCode::NotFound,
+      (_, None) => ReturnCode::InvalidTarget,
+    }
+  }
+
+  fn with_structure(creep_name: &str, structure_id: RawObjectId, action: impl Fn(&Creep, &Structure) -> ReturnCode) -> ReturnCode {
+    match (Self::get_creep(creep_name), ObjectId::<Structure>::from(structure_id).resolve()) {
+      (Some(creep), Some(structure)) => action(&creep, &structure),
+      (None, _) => ReturnCode::NotFound,
+      (_, None) => ReturnCode::InvalidTarget,
+    }
+  }
+}
+
+impl GameWorld for ScreepsWorld {
+  fn time(&self) -> u32 {
+    game::time()
+  }
+
+  fn raw_memory(&self) -> String {
+    String::from(screeps::raw_memory::RawMemory::get())
+  }
+
+  fn set_raw_memory(&mut self, serialized: &str) {
+    screeps::raw_memory::RawMemory::set(&serialized.into());
+  }
+
+  fn room_names(&self) -> Vec<RoomName> {
+    game::rooms().keys().collect()
+  }
+
+  fn room(&self, room_name: RoomName) -> Option<RoomState> {
+    game::rooms().get(room_name).map(|room| RoomState {
+      name: room.name(),
+      energy_available: room.energy_available(),
+      energy_capacity_available: room.energy_capacity_available(),
+    })
+  }
+
+  fn creep(&self, creep_name: &str) -> Option<CreepState> {
+    Self::get_creep(creep_name).map(|creep| Self::creep_state(&creep))
+  }
+
+  fn creep_names(&self) -> Vec<String> {
+    game::creeps().keys().collect()
+  }
+
+  fn source(&self, source_id: RawObjectId) -> Option<SourceState> {
+    ObjectId::<Source>::from(source_id).resolve().map(|source| Self::source_state(&source))
+  }
+
+  fn find_sources(&self, room_name: RoomName) -> Vec<SourceState> {
+    match game::rooms().get(room_name) {
+      Some(room) => room.find(find::SOURCES).iter().map(Self::source_state).collect(),
+      None => vec![],
+    }
+  }
+
+  fn structure(&self, structure_id: RawObjectId) -> Option<StructureState> {
+    game::get_object_by_id_erased(&structure_id)
+      .and_then(|object| object.dyn_into::<screeps::Structure>().ok())
+      .map(|structure| Self::structure_state(&StructureObject::from(structure)))
+  }
+
+  fn find_my_structures(&self, room_name: RoomName) -> Vec<StructureState> {
+    match game::rooms().get(room_name) {
+      Some(room) => room.find(find::MY_STRUCTURES).iter().map(Self::structure_state).collect(),
+      None => vec![],
+    }
+  }
+
+  fn find_structures(&self, room_name: RoomName) -> Vec<StructureState> {
+    match game::rooms().get(room_name) {
+      Some(room) => room.find(find::STRUCTURES).iter().map(Self::structure_state).collect(),
+      None => vec![],
+    }
+  }
+
+  fn find_construction_sites(&self, room_name: RoomName) -> Vec<ConstructionSiteState> {
+    match game::rooms().get(room_name) {
+      Some(room) => room.find(find::MY_CONSTRUCTION_SITES).iter().filter_map(Self::construction_site_state).collect(),
+      None => vec![],
+    }
+  }
+
+  fn terrain(&self, pos: Position) -> Terrain {
+    let (x, y) = super::position_xy(pos);
+    match screeps::RoomTerrain::new(pos.room_name()) {
+      Some(terrain) => terrain.get(x, y),
+      None => Terrain::Wall,
+    }
+  }
+
+  fn room_terrain(&self, room_name: RoomName) -> Vec<Terrain> {
+    let terrain = screeps::RoomTerrain::new(room_name);
+    (0..ROOM_SIZE.1 as u8)
+      .flat_map(|y| (0..ROOM_SIZE.0 as u8).map(move |x| (x, y)))
+      .map(|(x, y)| terrain.as_ref().map_or(Terrain::Wall, |terrain| terrain.get(x, y)))
+      .collect()
+  }
+
+  fn object_pos(&self, id: RawObjectId) -> Option<Position> {
+    game::get_object_by_id_erased(&id).map(|object| object.pos().into())
+  }
+
+  fn find_path(&self, from: Position, to: Position, range: u32) -> Option<Vec<Position>> {
+    let options = SearchOptions::new(Self::room_costs).plain_cost(2).swamp_cost(10);
+    let result = screeps::pathfinder::search(from, to, range, Some(options));
+    if result.incomplete() {
+      None
+    } else {
+      Some(result.path())
+    }
+  }
+
+  fn look_for_energy(&self, pos: Position) -> Vec<ResourceState> {
+    match game::rooms().get(pos.room_name()) {
+      Some(room) => {
+        let (x, y) = super::position_xy(pos);
+        room.look_for_at_xy(look::ENERGY, x, y)
+          .iter()
+          .map(|resource| ResourceState {
+            id: resource.raw_id(),
+            pos: resource.pos().into(),
+            resource_type: resource.resource_type(),
+            amount: resource.amount(),
+          })
+          .collect()
+      },
+      None => vec![],
+    }
+  }
+
+  fn find_dropped_resources(&self, room_name: RoomName) -> Vec<ResourceState> {
+    match game::rooms().get(room_name) {
+      Some(room) => room.find(find::DROPPED_RESOURCES)
+        .iter()
+        .map(|resource| ResourceState {
+          id: resource.raw_id(),
+          pos: resource.pos().into(),
+          resource_type: resource.resource_type(),
+          amount: resource.amount(),
+        })
+        .collect(),
+      None => vec![],
+    }
+  }
+
+  fn segment(&self, segment_id: u8) -> Option<String> {
+    screeps::raw_memory::segments().get(segment_id)
+  }
+
+  fn set_segment(&mut self, segment_id: u8, data: &str) {
+    let mut written = Self::written_segments();
+    if !written.1.contains(&segment_id) {
+      written.1.push(segment_id);
+    }
+    screeps::raw_memory::segments().set(segment_id, data.to_owned());
+  }
+
+  fn segment_writes_left(&self) -> usize {
+    MAX_ACTIVE_SEGMENTS.saturating_sub(Self::written_segments().1.len())
+  }
+
+  fn set_active_segments(&mut self, segment_ids: &[u8]) {
+    screeps::raw_memory::RawMemory::set_active_segments(segment_ids);
+  }
+
+  fn notify(&mut self, message: &str) {
+    game::notify(message, None);
+  }
+
+  fn validate(&self, intent: &Intent) -> ReturnCode {
+    intent.check(self)
+  }
+
+  fn creep_move_direction(&mut self, creep_name: &str, direction: Direction) -> ReturnCode {
+    match Self::get_creep(creep_name) {
+      Some(creep) => creep.move_direction(direction),
+      None => ReturnCode::NotFound,
+    }
+  }
+
+  fn creep_move_to(&mut self, creep_name: &str, target: Position) -> ReturnCode {
+    match Self::get_creep(creep_name) {
+      Some(creep) => creep.move_to(target),
+      None => ReturnCode::NotFound,
+    }
+  }
+
+  fn creep_harvest(&mut self, creep_name: &str, source_id: RawObjectId) -> ReturnCode {
+    match (Self::get_creep(creep_name), ObjectId::<Source>::from(source_id).resolve()) {
+      (Some(creep), Some(source)) => creep.harvest(&source),
+      (None, _) => ReturnCode::NotFound,
+      (_, None) => ReturnCode::InvalidTarget,
+    }
+  }
+
+  fn creep_pickup(&mut self, creep_name: &str, resource_id: RawObjectId) -> ReturnCode {
+    match (Self::get_creep(creep_name), ObjectId::<screeps::Resource>::from(resource_id).resolve()) {
+      (Some(creep), Some(resource)) => creep.pickup(&resource),
+      (None, _) => ReturnCode::NotFound,
+      (_, None) => ReturnCode::InvalidTarget,
+    }
+  }
+
+  fn creep_transfer(&mut self, creep_name: &str, target_id: RawObjectId, resource_type: ResourceType, amount: Option<u32>) -> ReturnCode {
+    let creep = match Self::get_creep(creep_name) {
+      Some(creep) => creep,
+      None => return ReturnCode::NotFound,
+    };
+    match game::get_object_by_id_erased(&target_id) {
+      Some(object) => {
+        if let Some(target) = object.dyn_ref::<Creep>() {
+          Self::transfer_to(&creep, target, resource_type, amount)
+        } else if let Some(target) = object.dyn_ref::<StructureSpawn>() {
+          Self::transfer_to(&creep, target, resource_type, amount)
+        } else if let Some(target) = object.dyn_ref::<StructureExtension>() {
+          Self::transfer_to(&creep, target, resource_type, amount)
+        } else if let Some(target) = object.dyn_ref::<StructureContainer>() {
+          Self::transfer_to(&creep, target, resource_type, amount)
+        } else if let Some(target) = object.dyn_ref::<StructureLink>() {
+          Self::transfer_to(&creep, target, resource_type, amount)
+        } else {
+          ReturnCode::InvalidTarget
+        }
+      },
+      None => ReturnCode::InvalidTarget,
+    }
+  }
+
+  fn creep_withdraw(&mut self, creep_name: &str, target_id: RawObjectId, resource_type: ResourceType, amount: Option<u32>) -> ReturnCode {
+    let creep = match Self::get_creep(creep_name) {
+      Some(creep) => creep,
+      None => return ReturnCode::NotFound,
+    };
+    match game::get_object_by_id_erased(&target_id) {
+      Some(object) => {
+        if let Some(target) = object.dyn_ref::<StructureSpawn>() {
+          Self::withdraw_from(&creep, target, resource_type, amount)
+        } else if let Some(target) = object.dyn_ref::<StructureExtension>() {
+          Self::withdraw_from(&creep, target, resource_type, amount)
+        } else if let Some(target) = object.dyn_ref::<StructureContainer>() {
+          Self::withdraw_from(&creep, target, resource_type, amount)
+        } else if let Some(target) = object.dyn_ref::<StructureLink>() {
+          Self::withdraw_from(&creep, target, resource_type, amount)
+        } else if let Some(target) = object.dyn_ref::<screeps::StructureStorage>() {
+          Self::withdraw_from(&creep, target, resource_type, amount)
+        } else if let Some(target) = object.dyn_ref::<screeps::Tombstone>() {
+          Self::withdraw_from(&creep, target, resource_type, amount)
+        } else if let Some(target) = object.dyn_ref::<screeps::Ruin>() {
+          Self::withdraw_from(&creep, target, resource_type, amount)
+        } else {
+          ReturnCode::InvalidTarget
+        }
+      },
+      None => ReturnCode::InvalidTarget,
+    }
+  }
+
+  fn creep_drop(&mut self, creep_name: &str, resource_type: ResourceType, amount: Option<u32>) -> ReturnCode {
+    match Self::get_creep(creep_name) {
+      Some(creep) => creep.drop(resource_type, amount),
+      None => ReturnCode::NotFound,
+    }
+  }
+
+  fn creep_build(&mut self, creep_name: &str, site_id: RawObjectId) -> ReturnCode {
+    match (Self::get_creep(creep_name), ObjectId::<ConstructionSite>::from(site_id).resolve()) {
+      (Some(creep), Some(site)) => creep.build(&site),
+      (None, _) => ReturnCode::NotFound,
+      (_, None) => ReturnCode::InvalidTarget,
+    }
+  }
+
+  fn creep_repair(&mut self, creep_name: &str, target_id: RawObjectId) -> ReturnCode {
+    Self::with_structure(creep_name, target_id, |creep, structure| creep.repair(structure))
+  }
+
+  fn creep_upgrade_controller(&mut self, creep_name: &str, controller_id: RawObjectId) -> ReturnCode {
+    Self::with_controller(creep_name, controller_id, |creep, controller| creep.upgrade_controller(controller))
+  }
+
+  fn creep_dismantle(&mut self, creep_name: &str, target_id: RawObjectId) -> ReturnCode {
+    Self::with_structure(creep_name, target_id, |creep, structure| creep.dismantle(structure))
+  }
+
+  fn creep_claim_controller(&mut self, creep_name: &str, controller_id: RawObjectId) -> ReturnCode {
+    Self::with_controller(creep_name, controller_id, |creep, controller| creep.claim_controller(controller))
+  }
+
+  fn creep_reserve_controller(&mut self, creep_name: &str, controller_id: RawObjectId) -> ReturnCode {
+    Self::with_controller(creep_name, controller_id, |creep, controller| creep.reserve_controller(controller))
+  }
+
+  fn creep_heal(&mut self, creep_name: &str, target_id: RawObjectId) -> ReturnCode {
+    match (Self::get_creep(creep_name), ObjectId::<Creep>::from(target_id).resolve()) {
+      (Some(creep), Some(target)) => creep.heal(&target),
+      (None, _) => ReturnCode::NotFound,
+      (_, None) => ReturnCode::InvalidTarget,
+    }
+  }
+
+  fn creep_attack(&mut self, creep_name: &str, target_id: RawObjectId) -> ReturnCode {
+    match Self::get_creep(creep_name) {
+      Some(creep) => match game::get_object_by_id_erased(&target_id) {
+        // Creeps and structures are the only things the hive attacks
+        Some(object) => {
+          if let Some(target) = object.dyn_ref::<Creep>() {
+            creep.attack(target)
+          } else if let Some(target) = object.dyn_ref::<Structure>() {
+            creep.attack(target)
+          } else {
+            ReturnCode::InvalidTarget
+          }
+        },
+        None => ReturnCode::InvalidTarget,
+      },
+      None => ReturnCode::NotFound,
+    }
+  }
+
+  fn creep_ranged_attack(&mut self, creep_name: &str, target_id: RawObjectId) -> ReturnCode {
+    match Self::get_creep(creep_name) {
+      Some(creep) => match game::get_object_by_id_erased(&target_id) {
+        // Creeps and structures are the only things the hive attacks
+        Some(object) => {
+          if let Some(target) = object.dyn_ref::<Creep>() {
+            creep.ranged_attack(target)
+          } else if let Some(target) = object.dyn_ref::<Structure>() {
+            creep.ranged_attack(target)
+          } else {
+            ReturnCode::InvalidTarget
+          }
+        },
+        None => ReturnCode::InvalidTarget,
+      },
+      None => ReturnCode::NotFound,
+    }
+  }
+
+  fn creep_pull(&mut self, creep_name: &str, pulled_name: &str) -> ReturnCode {
+    match (Self::get_creep(creep_name), Self::get_creep(pulled_name)) {
+      (Some(creep), Some(pulled)) => creep.pull(&pulled),
+      (None, _) => ReturnCode::NotFound,
+      (_, None) => ReturnCode::InvalidTarget,
+    }
+  }
+
+  fn creep_move_pulled_by(&mut self, creep_name: &str, puller_name: &str) -> ReturnCode {
+    match (Self::get_creep(creep_name), Self::get_creep(puller_name)) {
+      (Some(creep), Some(puller)) => creep.move_pulled_by(&puller),
+      (None, _) => ReturnCode::NotFound,
+      (_, None) => ReturnCode::InvalidTarget,
+    }
+  }
+
+  fn spawn_creep(&mut self, spawn_id: RawObjectId, body: &[Part], creep_name: &str) -> ReturnCode {
+    match ObjectId::<StructureSpawn>::from(spawn_id).resolve() {
+      Some(spawn) => spawn.spawn_creep(body, creep_name),
+      None => ReturnCode::InvalidTarget,
+    }
+  }
+
+  fn create_construction_site(&mut self, pos: Position, structure_type: StructureType) -> ReturnCode {
+    match game::rooms().get(pos.room_name()) {
+      Some(room) => {
+        let (x, y) = super::position_xy(pos);
+        room.create_construction_site(x, y, structure_type, None)
+      },
+      None => ReturnCode::NotInRange,
+    }
+  }
+
+  fn link_transfer_energy(&mut self, link_id: RawObjectId, target_id: RawObjectId, amount: Option<u32>) -> ReturnCode {
+    match (ObjectId::<StructureLink>::from(link_id).resolve(), ObjectId::<StructureLink>::from(target_id).resolve()) {
+      (Some(link), Some(target)) => link.transfer_energy(&target, amount),
+      _ => ReturnCode::InvalidTarget,
+    }
+  }
+}
@@ -0,0 +1,126 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use kaoscreeps_rust::{
+  constants::{BACKUP_SLOT_SEGMENTS, MAX_ACTIVE_SEGMENTS, SEGMENT_SIZE_LIMIT},
+  hive::tasks::Task,
+  memory::{backup::{self, Restore, Stored}, cache::MemoryCache},
+  scenario::Scenario,
+  world::{GameWorld, MemoryWorld},
+};
+use screeps::Direction;
+
+fn world() -> MemoryWorld {
+  let mut world = Scenario::load("scenarios/haul_to_spawn.json").unwrap().build_world().unwrap();
+  world.raw_memory = String::new();
+  world
+}
+
+fn activate_backups(world: &mut MemoryWorld) {
+  world.active_segments.extend(backup::all_segments());
+}
+
+#[test]
+fn backups_round_trip() {
+  let mut world = MemoryWorld::new();
+  assert_eq!(backup::store(&mut world, 1, "{\"version\":[0,0,0,0]}"), Stored::Written);
+  activate_backups(&mut world);
+
+  let restored = backup::read(&world, 1).unwrap();
+  assert_eq!(restored.time, world.time);
+  assert_eq!(restored.serialized, "{\"version\":[0,0,0,0]}");
+}
+
+#[test]
+fn damaged_backups_fail_their_checksum() {
+  let mut world = MemoryWorld::new();
+  assert_eq!(backup::store(&mut world, 0, "{\"version\":[0,0,0,0]}"), Stored::Written);
+  activate_backups(&mut world);
+
+  let segment_id = backup::slot_segments(0)[0];
+  let damaged = world.segments[&segment_id].replace("0,0,0,0", "0,0,0,1");
+  world.segments.insert(segment_id, damaged);
+  assert_eq!(backup::read(&world, 0), None);
+  assert_eq!(backup::restore(&mut world), Restore::Unavailable);
+}
+
+#[test]
+fn newest_backup_is_restored() {
+  let mut world = MemoryWorld::new();
+  assert_eq!(backup::store(&mut world, 0, "{}"), Stored::Written);
+  world.time += 10;
+  assert_eq!(backup::store(&mut world, 1, "{\"newer\":true}"), Stored::Written);
+
+  assert_eq!(backup::restore(&mut world), Restore::Pending);
+  world.end_tick();
+  match backup::restore(&mut world) {
+    Restore::Found(restored) => assert_eq!(restored.serialized, "{\"newer\":true}"),
+    other => panic!("expected a backup, got {:?}", other),
+  }
+}
+
+#[test]
+fn backups_wait_for_enough_segment_writes() {
+  let mut world = MemoryWorld::new();
+  for segment_id in 0..(MAX_ACTIVE_SEGMENTS as u8) {
+    world.set_segment(segment_id, "taken");
+  }
+  assert_eq!(backup::store(&mut world, 0, "{}"), Stored::Deferred);
+  assert!(backup::slot_segments(0).iter().all(|segment_id| !world.segments.contains_key(segment_id)));
+
+  world.end_tick();
+  assert_eq!(backup::store(&mut world, 0, "{}"), Stored::Written);
+  // Only the segment that holds the backup is written
+  assert_eq!(backup::slot_segments(0).iter().filter(|segment_id| world.segments.contains_key(segment_id)).count(), 1);
+}
+
+#[test]
+fn smaller_backups_ignore_segments_left_over_from_larger_ones() {
+  let mut world = MemoryWorld::new();
+  let larger = format!("\"{}\"", "x".repeat(SEGMENT_SIZE_LIMIT));
+  assert_eq!(backup::store(&mut world, 0, &larger), Stored::Written);
+  world.end_tick();
+  assert_eq!(backup::store(&mut world, 0, "{}"), Stored::Written);
+  activate_backups(&mut world);
+
+  assert_eq!(backup::read(&world, 0).unwrap().serialized, "{}");
+}
+
+#[test]
+fn memory_too_large_for_a_slot_is_reported() {
+  let mut world = MemoryWorld::new();
+  let serialized = "x".repeat(SEGMENT_SIZE_LIMIT * BACKUP_SLOT_SEGMENTS as usize);
+  assert_eq!(backup::store(&mut world, 0, &serialized), Stored::TooLarge { segments: BACKUP_SLOT_SEGMENTS as usize + 1 });
+  assert!(world.segments.is_empty());
+}
+
+#[test]
+fn corrupted_memory_is_restored_from_backup() {
+  let mut world = world();
+  let mut cache = MemoryCache::new();
+  let mem = cache.get(&mut world).unwrap();
+  mem.hive.tasks.add_task(String::from("idle"), Task::Move(Direction::Top)).unwrap();
+  cache.persist(&mut world);
+
+  // Global reset with RawMemory mangled in the meantime
+  let mut cache = MemoryCache::new();
+  world.raw_memory.truncate(world.raw_memory.len() / 2);
+  assert!(cache.get(&mut world).is_none());
+  world.end_tick();
+
+  assert!(cache.get(&mut world).unwrap().hive.tasks.has_task("idle"));
+  assert_eq!(world.notifications.len(), 1);
+  assert!(world.notifications[0].contains("restored the backup"));
+}
+
+#[test]
+fn corrupted_memory_without_a_backup_starts_over_loudly() {
+  let mut world = world();
+  world.raw_memory = String::from("{ not memory");
+  let mut cache = MemoryCache::new();
+
+  assert!(cache.get(&mut world).is_none());
+  world.end_tick();
+  assert!(cache.get(&mut world).is_some());
+  assert_eq!(world.notifications.len(), 1);
+  assert!(world.notifications[0].contains("no backup"));
+}
@@ -0,0 +1,68 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use kaoscreeps_rust::hive::{body_planner::{BodyError, BodyTemplate, Ground, body_cost}, mine_room::MineRoom};
+use screeps::Part;
+
+#[test]
+fn segments_repeat_within_budget() {
+  let body = BodyTemplate::new(vec![Part::Work, Part::Carry]).plan(550).unwrap();
+  // Each segment is 150 energy of parts and 100 energy of MOVE on plain terrain
+  assert_eq!(body, vec![Part::Work, Part::Work, Part::Carry, Part::Carry, Part::Move, Part::Move, Part::Move, Part::Move]);
+  assert!(body_cost(&body) <= 550);
+}
+
+#[test]
+fn move_ratio_follows_ground() {
+  let count_moves = |ground| BodyTemplate::new(vec![]).with_prefix(vec![Part::Carry; 4]).with_movement(Some(ground)).plan(10_000).unwrap()
+    .into_iter().filter(|part| *part == Part::Move).count();
+  assert_eq!(count_moves(Ground::Road), 2);
+  assert_eq!(count_moves(Ground::Plain), 4);
+  assert_eq!(count_moves(Ground::Swamp), 20);
+}
+
+#[test]
+fn prefix_moves_count_towards_the_ratio() {
+  let body = BodyTemplate::new(vec![]).with_prefix(vec![Part::Move, Part::Carry, Part::Carry]).plan(300).unwrap();
+  assert_eq!(body, vec![Part::Carry, Part::Carry, Part::Move, Part::Move]);
+}
+
+#[test]
+fn never_exceeds_the_part_limit() {
+  let body = BodyTemplate::new(vec![Part::Carry]).with_max_parts(100).plan(1_000_000).unwrap();
+  assert_eq!(body.len(), 50);
+
+  let body = BodyTemplate::new(vec![Part::Work]).with_max_parts(5).with_movement(None).plan(10_000).unwrap();
+  assert_eq!(body, vec![Part::Work; 5]);
+}
+
+#[test]
+fn parts_are_ordered_for_durability() {
+  let body = BodyTemplate::new(vec![Part::Heal, Part::Attack])
+    .with_prefix(vec![Part::Tough, Part::Tough])
+    .with_movement(Some(Ground::Road))
+    .plan(10_000)
+    .unwrap();
+  let first_move = body.iter().position(|part| *part == Part::Move).unwrap();
+  assert!(body[..2].iter().all(|part| *part == Part::Tough));
+  assert!(body[..first_move].iter().all(|part| *part != Part::Heal));
+  assert_eq!(body.last(), Some(&Part::Heal));
+}
+
+#[test]
+fn reports_bodies_that_cannot_be_spawned() {
+  assert_eq!(BodyTemplate::new(vec![]).plan(300), Err(BodyError::Empty));
+  assert_eq!(BodyTemplate::new(vec![Part::Work]).plan(100), Err(BodyError::TooExpensive { cost: 150, budget: 100 }));
+  assert_eq!(
+    BodyTemplate::new(vec![Part::Carry; 30]).plan(10_000),
+    Err(BodyError::TooLarge { parts: 60, max_parts: 50 }),
+  );
+}
+
+#[test]
+fn mine_room_bodies_respect_the_budget() {
+  assert!(matches!(MineRoom::get_harvester_body(0, 50), Err(BodyError::TooExpensive { .. })));
+  assert_eq!(MineRoom::get_harvester_body(0, 300).unwrap(), vec![Part::Work; 3]);
+  assert_eq!(MineRoom::get_harvester_body(0, 1000).unwrap(), vec![Part::Work; 5]);
+  assert_eq!(MineRoom::get_hauler_body(2, 100).unwrap(), vec![Part::Carry, Part::Move]);
+  assert_eq!(MineRoom::get_hauler_body(2, 300).unwrap(), vec![Part::Carry, Part::Carry, Part::Move, Part::Move]);
+}
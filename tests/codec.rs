@@ -0,0 +1,85 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use serde_json::{json, Value};
+
+use kaoscreeps_rust::memory::{MEM_VERSION, codec::{self, Codec, pack, unpack}, migrations::migrate_str};
+
+fn memory_v0_0_0_0() -> Value {
+  json!({
+    "version": [0, 0, 0, 0],
+    "hive": {
+      "tasks": {
+        "task_list": {
+          "hauler:5bbcaa9099171b1f4c0f1a01": { "Harvest": "5bbcaa9099171b1f4c0f1a01" }
+        }
+      },
+      "mine_rooms": {
+        "W1N1": {
+          "room_name": "W1N1",
+          "spawn_room_name": "W1N1",
+          "energy_drop_off": { "Spawn": "5bbcaa9099171b1f4c0f1b01" },
+          "source_ids": ["5bbcaa9099171b1f4c0f1a01"]
+        }
+      },
+      "spawn_rooms": {
+        "W1N1": {
+          "room_name": "W1N1",
+          "spawn_ids": ["5bbcaa9099171b1f4c0f1b01"],
+          "extension_ids": []
+        }
+      }
+    }
+  })
+}
+
+#[test]
+fn packing_round_trips_every_length() {
+  for length in 0..64 {
+    let bytes = (0..length).map(|i| (i * 37 + 11) as u8).collect::<Vec<_>>();
+    assert_eq!(unpack(&pack(&bytes), bytes.len()).unwrap(), bytes);
+  }
+}
+
+#[test]
+fn packed_strings_stay_clear_of_surrogates_and_control_characters() {
+  let bytes = (0..=255u8).rev().cycle().take(1024).collect::<Vec<_>>();
+  for character in pack(&bytes).encode_utf16() {
+    assert!(character >= 0x100 && !(0xD800..0xE000).contains(&character));
+  }
+}
+
+#[test]
+fn truncated_binary_is_rejected() {
+  let packed = pack(&[1, 2, 3, 4, 5, 6, 7, 8]);
+  assert!(unpack(&packed[..packed.char_indices().nth(2).unwrap().0], 8).is_err());
+}
+
+#[test]
+fn codec_is_detected_from_the_header() {
+  let value = memory_v0_0_0_0();
+  assert_eq!(Codec::detect(&codec::encode(&value, Codec::Json).unwrap()), Codec::Json);
+  assert_eq!(Codec::detect(&codec::encode(&value, Codec::Binary).unwrap()), Codec::Binary);
+}
+
+#[test]
+fn memory_survives_switching_codecs() {
+  let memory = migrate_str(&memory_v0_0_0_0().to_string()).unwrap();
+  let json = codec::encode(&memory, Codec::Json).unwrap();
+
+  let binary = codec::encode(&memory, Codec::Binary).unwrap();
+  assert!(binary.encode_utf16().count() < json.len());
+
+  let from_binary: kaoscreeps_rust::memory::Memory = codec::decode(&binary).unwrap();
+  assert_eq!(from_binary.version, MEM_VERSION);
+  // Compared as values, map order is not stable between instances
+  assert_eq!(
+    serde_json::from_str::<Value>(&codec::encode(&from_binary, Codec::Json).unwrap()).unwrap(),
+    serde_json::from_str::<Value>(&json).unwrap(),
+  );
+}
+
+#[test]
+fn old_binary_memory_is_migrated() {
+  let binary = codec::encode(&memory_v0_0_0_0(), Codec::Binary).unwrap();
+  assert_eq!(migrate_str(&binary).unwrap().version, MEM_VERSION);
+}
@@ -0,0 +1,64 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use kaoscreeps_rust::{
+  commands::{add_task, cancel_tasks, list_tasks},
+  hive::{group_tasks::{GroupAction, GroupTask}, tasks::{Task, TaskPriority, Tasks}},
+  world::{MemoryWorld, new_position},
+};
+use screeps::{Part, RawObjectId, ResourceType, RoomName, StructureType};
+
+fn world() -> (MemoryWorld, RawObjectId) {
+  let room_name = RoomName::new("W1N1").unwrap();
+  let mut world = MemoryWorld::new();
+  let spawn_id = world.add_structure(StructureType::Spawn, new_position(25, 25, room_name), 300, 300);
+  world.add_creep("worker", new_position(25, 26, room_name), vec![Part::Work, Part::Carry, Part::Move]);
+  (world, spawn_id)
+}
+
+#[test]
+fn tasks_from_json_are_added_at_the_requested_priority() {
+  let (world, spawn_id) = world();
+  let mut tasks = Tasks::new();
+  let withdraw = serde_json::to_string(&Task::Withdraw { target: spawn_id, resource_type: ResourceType::Energy, amount: None }).unwrap();
+  let drop = serde_json::to_string(&Task::Drop { resource_type: ResourceType::Energy, amount: None }).unwrap();
+
+  add_task(&mut tasks, &world, "worker", &withdraw, None).unwrap();
+  add_task(&mut tasks, &world, "worker", &drop, Some("Urgent")).unwrap();
+  assert_eq!(tasks.current_task("worker").unwrap().priority, TaskPriority::Urgent);
+
+  let listed = list_tasks(&tasks, Some("worker"));
+  let lines = listed.lines().collect::<Vec<_>>();
+  assert_eq!(lines.len(), 2);
+  assert!(lines[0].starts_with("worker [Urgent]") && lines[0].contains("Drop"));
+  assert!(lines[1].starts_with("worker [Normal]") && lines[1].contains("Withdraw"));
+}
+
+#[test]
+fn invalid_tasks_are_rejected() {
+  let (world, _) = world();
+  let mut tasks = Tasks::new();
+  let drop = serde_json::to_string(&Task::Drop { resource_type: ResourceType::Energy, amount: None }).unwrap();
+  let empty_steps = serde_json::to_string(&Task::MultiStep(Default::default())).unwrap();
+
+  assert!(add_task(&mut tasks, &world, "nobody", &drop, None).is_err());
+  assert!(add_task(&mut tasks, &world, "worker", "{\"Fly\":1}", None).is_err());
+  assert!(add_task(&mut tasks, &world, "worker", &drop, Some("Whenever")).is_err());
+  assert!(add_task(&mut tasks, &world, "worker", &empty_steps, None).is_err());
+  assert!(add_task(&mut tasks, &world, "worker", &serde_json::to_string(&Task::Tow(String::from("nobody"), screeps::Direction::Top)).unwrap(), None).is_err());
+  assert!(!tasks.has_task("worker"));
+}
+
+#[test]
+fn cancelling_drops_the_stack_and_any_group() {
+  let (mut world, _) = world();
+  world.add_creep("towed", new_position(25, 27, RoomName::new("W1N1").unwrap()), vec![Part::Work]);
+  let mut tasks = Tasks::new();
+  tasks.add_task(String::from("worker"), Task::Drop { resource_type: ResourceType::Energy, amount: None }).unwrap();
+  tasks.add_group(String::from("leader"), GroupTask::new(vec![String::from("towed")], GroupAction::Tow { target: new_position(10, 10, RoomName::new("W1N1").unwrap()), range: 1, path: Default::default() })).unwrap();
+
+  assert!(list_tasks(&tasks, Some("towed")).starts_with("leader leading [\"towed\"]"));
+  assert!(cancel_tasks(&mut tasks, "worker").is_ok());
+  assert!(cancel_tasks(&mut tasks, "towed").is_ok());
+  assert!(cancel_tasks(&mut tasks, "worker").is_err());
+  assert_eq!(list_tasks(&tasks, None), "No tasks");
+}
@@ -0,0 +1,78 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use kaoscreeps_rust::{
+  constants::MEMORY_SAVE_INTERVAL,
+  hive::tasks::Task,
+  memory::{cache::MemoryCache, codec::{self, Codec}},
+  scenario::Scenario,
+  world::MemoryWorld,
+};
+use screeps::Direction;
+
+fn world() -> MemoryWorld {
+  let mut world = Scenario::load("scenarios/haul_to_spawn.json").unwrap().build_world().unwrap();
+  world.raw_memory = String::new();
+  world
+}
+
+// Loads fresh memory and saves it, leaving the cache clean
+fn primed_cache(world: &mut MemoryWorld) -> MemoryCache {
+  let mut cache = MemoryCache::new();
+  cache.get(world).unwrap();
+  cache.persist(world);
+  cache
+}
+
+#[test]
+fn clean_memory_waits_for_the_save_interval() {
+  let mut world = world();
+  let mut cache = primed_cache(&mut world);
+  let saved = world.raw_memory.to_owned();
+
+  cache.get(&mut world).unwrap().hive.tasks.add_task(String::from("idle"), Task::Move(Direction::Top)).unwrap();
+  world.time += 1;
+  cache.persist(&mut world);
+  assert_eq!(world.raw_memory, saved);
+
+  world.time += MEMORY_SAVE_INTERVAL;
+  cache.persist(&mut world);
+  assert_ne!(world.raw_memory, saved);
+}
+
+#[test]
+fn dirty_memory_is_saved_immediately() {
+  let mut world = world();
+  let mut cache = primed_cache(&mut world);
+  let saved = world.raw_memory.to_owned();
+
+  let mem = cache.get(&mut world).unwrap();
+  mem.hive.tasks.add_task(String::from("idle"), Task::Move(Direction::Top)).unwrap();
+  mem.mark_dirty();
+  world.time += 1;
+  cache.persist(&mut world);
+  assert_ne!(world.raw_memory, saved);
+}
+
+#[test]
+fn external_edits_are_reloaded() {
+  let mut world = world();
+  let mut cache = primed_cache(&mut world);
+
+  let mut edited = cache.get(&mut world).unwrap().to_owned();
+  edited.settings.log_intents = true;
+  world.raw_memory = codec::encode(&edited, Codec::Json).unwrap();
+
+  assert!(cache.get(&mut world).unwrap().settings.log_intents);
+}
+
+#[test]
+fn cached_memory_is_kept_between_ticks() {
+  let mut world = world();
+  let mut cache = primed_cache(&mut world);
+
+  cache.get(&mut world).unwrap().hive.tasks.add_task(String::from("idle"), Task::Move(Direction::Top)).unwrap();
+  world.time += 1;
+  cache.persist(&mut world);
+
+  assert!(cache.get(&mut world).unwrap().hive.tasks.has_task("idle"));
+}
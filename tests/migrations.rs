@@ -0,0 +1,166 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use serde_json::{json, Value};
+
+use kaoscreeps_rust::constants::MEMORY_SAVE_INTERVAL;
+use kaoscreeps_rust::memory::{MEM_VERSION, migrations::{MIGRATIONS, migrate, migrate_str, version_of}};
+
+fn memory_v0_0_0_0() -> Value {
+  json!({
+    "version": [0, 0, 0, 0],
+    "hive": {
+      "tasks": {
+        "task_list": {
+          "hauler:5bbcaa9099171b1f4c0f1a01": { "Harvest": "5bbcaa9099171b1f4c0f1a01" }
+        }
+      },
+      "mine_rooms": {
+        "W1N1": {
+          "room_name": "W1N1",
+          "spawn_room_name": "W1N1",
+          "energy_drop_off": { "Spawn": "5bbcaa9099171b1f4c0f1b01" },
+          "source_ids": ["5bbcaa9099171b1f4c0f1a01"]
+        }
+      },
+      "spawn_rooms": {
+        "W1N1": {
+          "room_name": "W1N1",
+          "spawn_ids": ["5bbcaa9099171b1f4c0f1b01"],
+          "extension_ids": []
+        }
+      }
+    }
+  })
+}
+
+fn step(from: (u8,u8,u8,u8)) -> fn(Value) -> Result<Value,String> {
+  MIGRATIONS.iter().find(|migration| migration.from == from).unwrap().migrate
+}
+
+#[test]
+fn migrations_form_a_chain_to_the_current_version() {
+  for pair in MIGRATIONS.windows(2) {
+    assert_eq!(pair[0].to, pair[1].from);
+  }
+  assert_eq!(MIGRATIONS.last().unwrap().to, MEM_VERSION);
+}
+
+#[test]
+fn v0_0_0_0_gains_settings_and_recorder() {
+  let migrated = step((0,0,0,0))(memory_v0_0_0_0()).unwrap();
+  assert_eq!(migrated["settings"], json!({ "dry_run": false, "log_intents": false }));
+  assert_eq!(migrated["recorder"], json!({ "remaining_ticks": 0, "recorded": [] }));
+  assert_eq!(migrated["hive"], memory_v0_0_0_0()["hive"]);
+}
+
+#[test]
+fn v0_1_0_0_gains_a_save_interval() {
+  let memory = step((0,0,0,0))(memory_v0_0_0_0()).unwrap();
+  let migrated = step((0,1,0,0))(memory).unwrap();
+  assert_eq!(migrated["settings"]["save_interval"], json!(MEMORY_SAVE_INTERVAL));
+  assert_eq!(migrated["settings"]["dry_run"], json!(false));
+}
+
+#[test]
+fn v0_2_0_0_gains_segment_storage() {
+  let migrated = step((0,2,0,0))(memory_v0_0_0_0()).unwrap();
+  assert_eq!(migrated["storage"], json!({ "shards": {} }));
+}
+
+#[test]
+fn v0_3_0_0_tasks_are_scheduled() {
+  let migrated = step((0,3,0,0))(memory_v0_0_0_0()).unwrap();
+  let scheduled = &migrated["hive"]["tasks"]["task_list"]["hauler:5bbcaa9099171b1f4c0f1a01"];
+  assert_eq!(scheduled["task"], json!({ "Harvest": "5bbcaa9099171b1f4c0f1a01" }));
+  assert_eq!(scheduled["retries"], json!(0));
+}
+
+#[test]
+fn v0_4_0_0_gains_task_groups() {
+  let migrated = step((0,4,0,0))(memory_v0_0_0_0()).unwrap();
+  assert_eq!(migrated["hive"]["tasks"]["groups"], json!({}));
+  assert_eq!(migrated["hive"]["tasks"]["task_list"], memory_v0_0_0_0()["hive"]["tasks"]["task_list"]);
+}
+
+#[test]
+fn v0_5_0_0_tasks_gain_owners_and_events() {
+  let memory = step((0,3,0,0))(memory_v0_0_0_0()).unwrap();
+  let migrated = step((0,5,0,0))(memory).unwrap();
+  assert_eq!(migrated["hive"]["tasks"]["task_list"]["hauler:5bbcaa9099171b1f4c0f1a01"]["owner"], Value::Null);
+  assert_eq!(migrated["hive"]["tasks"]["events"], json!([]));
+}
+
+#[test]
+fn v0_6_0_0_tasks_become_stacks() {
+  let memory = step((0,3,0,0))(memory_v0_0_0_0()).unwrap();
+  let migrated = step((0,6,0,0))(memory).unwrap();
+  let task_stack = &migrated["hive"]["tasks"]["task_list"]["hauler:5bbcaa9099171b1f4c0f1a01"];
+  assert_eq!(task_stack.as_array().unwrap().len(), 1);
+  assert_eq!(task_stack[0]["priority"], json!("Normal"));
+  assert_eq!(task_stack[0]["task"], json!({ "Harvest": "5bbcaa9099171b1f4c0f1a01" }));
+}
+
+#[test]
+fn v0_7_0_0_failed_events_carry_a_task_error() {
+  let mut memory = memory_v0_0_0_0();
+  memory["hive"]["tasks"]["events"] = json!([
+    { "owner": { "MineRoom": "W1N1" }, "creep_name": "hauler", "status": { "Failed": -7 }, "time": 12 },
+    { "owner": { "MineRoom": "W1N1" }, "creep_name": "harvester", "status": "Completed", "time": 12 },
+  ]);
+  let migrated = step((0,7,0,0))(memory).unwrap();
+  let events = &migrated["hive"]["tasks"]["events"];
+  assert_eq!(events[0]["status"]["Failed"], json!({ "path": [], "target": null, "return_codes": [-7], "time": 12 }));
+  assert_eq!(events[1]["status"], json!("Completed"));
+}
+
+#[test]
+fn v0_8_0_0_spawn_rooms_gain_a_queue() {
+  let migrated = step((0,8,0,0))(memory_v0_0_0_0()).unwrap();
+  assert_eq!(migrated["hive"]["spawn_rooms"]["W1N1"]["spawn_queue"], json!([]));
+  assert_eq!(migrated["hive"]["spawn_rooms"]["W1N1"]["spawn_ids"], memory_v0_0_0_0()["hive"]["spawn_rooms"]["W1N1"]["spawn_ids"]);
+}
+
+#[test]
+fn v0_9_0_0_mine_rooms_gain_round_trips() {
+  let migrated = step((0,9,0,0))(memory_v0_0_0_0()).unwrap();
+  assert_eq!(migrated["hive"]["mine_rooms"]["W1N1"]["round_trips"], json!({}));
+  assert_eq!(migrated["hive"]["mine_rooms"]["W1N1"]["source_ids"], memory_v0_0_0_0()["hive"]["mine_rooms"]["W1N1"]["source_ids"]);
+}
+
+#[test]
+fn v0_10_0_0_mine_rooms_gain_mining_modes() {
+  let migrated = step((0,10,0,0))(memory_v0_0_0_0()).unwrap();
+  assert_eq!(migrated["hive"]["mine_rooms"]["W1N1"]["mining_modes"], json!({}));
+}
+
+#[test]
+fn v0_11_0_0_spawn_requests_are_not_dispatched() {
+  let mut memory = memory_v0_0_0_0();
+  memory["hive"]["spawn_rooms"]["W1N1"]["spawn_queue"] = json!([
+    { "name": "hauler", "body": ["carry", "move"], "priority": "Normal", "requested": 10, "updated": 12 }
+  ]);
+  let migrated = step((0,11,0,0))(memory).unwrap();
+  assert_eq!(migrated["hive"]["spawn_rooms"]["W1N1"]["spawn_queue"][0]["dispatched"], Value::Null);
+  assert_eq!(migrated["hive"]["spawn_rooms"]["W1N1"]["spawn_queue"][0]["updated"], json!(12));
+}
+
+#[test]
+fn oldest_memory_migrates_to_current_without_losing_the_hive() {
+  let memory = migrate_str(&memory_v0_0_0_0().to_string()).unwrap();
+  assert_eq!(memory.version, MEM_VERSION);
+  assert!(memory.hive.mine_rooms.values().any(|mine_room| mine_room.room_name.to_string() == "W1N1"));
+  assert!(memory.hive.spawn_rooms.values().any(|spawn_room| spawn_room.room_name.to_string() == "W1N1"));
+}
+
+#[test]
+fn migrated_memory_records_the_new_version() {
+  let migrated = migrate(memory_v0_0_0_0()).unwrap();
+  assert_eq!(version_of(&migrated).unwrap(), MEM_VERSION);
+}
+
+#[test]
+fn unknown_versions_are_rejected() {
+  let mut memory = memory_v0_0_0_0();
+  memory["version"] = json!([9, 9, 9, 9]);
+  assert!(migrate(memory).is_err());
+}
@@ -0,0 +1,131 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use kaoscreeps_rust::{
+  hive::{Hive, role_names, body_planner::BodyError, mine_room::{HaulerPlan, MineRoom, MiningMode}},
+  world::{GameWorld, MemoryWorld, new_position},
+};
+use screeps::{Part, RawObjectId, RoomName, StructureType};
+
+// Spawn at 25,25 and a source ten tiles below it
+fn mining_world() -> (MemoryWorld, Hive, RoomName, RawObjectId) {
+  let room_name = RoomName::new("W1N1").unwrap();
+  let mut world = MemoryWorld::new();
+  world.add_structure(StructureType::Spawn, new_position(25, 25, room_name), 300, 300);
+  let source_id = world.add_source(new_position(25, 35, room_name), screeps::constants::SOURCE_ENERGY_CAPACITY);
+  let hive = Hive::new(&world, room_name);
+  world.end_tick(); // Hive::new already took the spawn for its first tick
+  (world, hive, room_name, source_id)
+}
+
+#[test]
+fn haulers_carry_a_round_trip_of_income() {
+  assert_eq!(MineRoom::plan_haulers(10, 10, 300), Ok(HaulerPlan { count: 1, carry_parts: 2 }));
+  assert_eq!(MineRoom::plan_haulers(10, 100, 5600), Ok(HaulerPlan { count: 1, carry_parts: 20 }));
+}
+
+#[test]
+fn haulers_are_split_when_one_cannot_carry_enough() {
+  // 300 energy only pays for three CARRY with their MOVE
+  assert_eq!(MineRoom::plan_haulers(10, 60, 300), Ok(HaulerPlan { count: 4, carry_parts: 3 }));
+  // 25 CARRY is the most that fits in 50 parts at full speed
+  assert_eq!(MineRoom::plan_haulers(10, 130, 10_000), Ok(HaulerPlan { count: 2, carry_parts: 13 }));
+  assert!(matches!(MineRoom::plan_haulers(10, 10, 50), Err(BodyError::TooExpensive { .. })));
+}
+
+#[test]
+fn mine_room_measures_the_round_trip_and_requests_every_hauler() {
+  let room_name = RoomName::new("W1N1").unwrap();
+  let mut world = MemoryWorld::new();
+  world.add_structure(StructureType::Spawn, new_position(25, 25, room_name), 300, 300);
+  let source_id = world.add_source(new_position(25, 35, room_name), screeps::constants::SOURCE_ENERGY_CAPACITY);
+  let mut hive = Hive::new(&world, room_name);
+  world.end_tick(); // Hive::new already took the spawn for its first tick
+
+  hive.run(&mut world);
+
+  // Nine steps each way, plus a tick at each end
+  assert_eq!(hive.mine_rooms[&room_name].round_trips.get(&String::from(source_id)), Some(&20));
+  let requested = hive.spawn_rooms[&room_name].pending_requests().iter()
+    .map(|request| request.name.to_owned())
+    .chain(world.creeps.keys().cloned())
+    .collect::<Vec<_>>();
+  assert!(requested.contains(&MineRoom::hauler_name(source_id, 0)));
+  assert!(requested.contains(&MineRoom::hauler_name(source_id, 1)));
+  assert!(!requested.contains(&MineRoom::hauler_name(source_id, 2)));
+}
+
+#[test]
+fn drop_harvesters_carry_while_it_beats_decay() {
+  // An unreserved source visited every fifteen ticks never piles up enough for CARRY to pay for itself
+  assert_eq!(MineRoom::drop_harvester_carry_parts(5, 15, 800), 0);
+  assert_eq!(MineRoom::drop_harvester_carry_parts(10, 10, 800), 1);
+  assert_eq!(MineRoom::drop_harvester_carry_parts(10, 100, 1200), 7);
+  // Only what is left of the budget after five WORK
+  assert_eq!(MineRoom::drop_harvester_carry_parts(10, 100, 700), 4);
+  // The other 45 parts of the body
+  assert_eq!(MineRoom::drop_harvester_carry_parts(10, 4000, 100_000), 45);
+  assert_eq!(MineRoom::drop_harvester_carry_parts(10, 100, 300), 0);
+}
+
+#[test]
+fn harvesters_only_carry_what_is_left_after_work() {
+  assert_eq!(MineRoom::get_harvester_body(2, 300).unwrap(), vec![Part::Work; 3]);
+  assert_eq!(MineRoom::get_harvester_body(2, 800).unwrap(), vec![Part::Work, Part::Work, Part::Work, Part::Work, Part::Work, Part::Carry, Part::Carry]);
+}
+
+#[test]
+fn container_mining_builds_a_container_on_the_access_tile() {
+  let (mut world, mut hive, room_name, source_id) = mining_world();
+  hive.mine_rooms.get_mut(&room_name).unwrap().set_mining_mode(source_id, MiningMode::Container);
+  world.add_creep(&(String::from("harvester:") + &String::from(source_id)), new_position(25, 34, room_name), vec![Part::Work, Part::Work, Part::Work, Part::Work, Part::Work, Part::Carry]);
+
+  hive.run(&mut world);
+  world.end_tick();
+  let site = world.find_construction_sites(room_name).into_iter().next().expect("a container site is placed");
+  assert_eq!((site.structure_type, site.pos), (StructureType::Container, new_position(25, 34, room_name)));
+
+  for _ in 0..1200 {
+    if !world.find_structures(room_name).iter().any(|structure| structure.structure_type == StructureType::Container) {
+      hive.run(&mut world);
+      world.end_tick();
+    }
+  }
+  let container = world.find_structures(room_name).into_iter().find(|structure| structure.structure_type == StructureType::Container);
+  assert_eq!(container.map(|container| container.pos), Some(new_position(25, 34, room_name)));
+}
+
+#[test]
+fn link_mining_sends_energy_to_the_receiver() {
+  let (mut world, mut hive, room_name, source_id) = mining_world();
+  let link = world.add_structure(StructureType::Link, new_position(27, 35, room_name), 0, 800);
+  let receiver = world.add_structure(StructureType::Link, new_position(25, 27, room_name), 0, 800);
+  hive.mine_rooms.get_mut(&room_name).unwrap().set_mining_mode(source_id, MiningMode::Link { link, receiver });
+  // Next to both the source and the link
+  world.add_creep(&(String::from("harvester:") + &String::from(source_id)), new_position(26, 34, room_name), vec![Part::Work, Part::Work, Part::Work, Part::Work, Part::Work, Part::Carry]);
+
+  for _ in 0..60 {
+    hive.run(&mut world);
+    world.end_tick();
+  }
+
+  // Haulers may already have taken some of it on to the spawn
+  assert!(world.structures[&receiver].energy > 0 || world.stats.energy_delivered > 0);
+  assert_eq!(hive.mine_rooms[&room_name].round_trips.get(&String::from(source_id)), Some(&4));
+}
+
+#[test]
+fn an_old_harvester_is_replaced_before_it_dies() {
+  let (mut world, mut hive, room_name, source_id) = mining_world();
+  let harvester_role = String::from("harvester:") + &String::from(source_id);
+  world.add_creep(&harvester_role, new_position(25, 34, room_name), vec![Part::Work, Part::Work, Part::Carry]);
+  let queued = |hive: &Hive| hive.spawn_rooms[&room_name].pending_requests().iter().map(|request| request.name.to_owned()).collect::<Vec<_>>();
+
+  hive.run(&mut world);
+  assert!(!queued(&hive).contains(&role_names(&harvester_role)[1]));
+
+  // Spawning three parts and walking half of the twenty tick round trip takes nineteen ticks
+  world.creeps.get_mut(&harvester_role).unwrap().ticks_to_live = Some(19);
+  world.end_tick();
+  hive.run(&mut world);
+  assert!(queued(&hive).contains(&role_names(&harvester_role)[1]) || world.creeps.contains_key(&role_names(&harvester_role)[1]));
+}
@@ -0,0 +1,69 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use kaoscreeps_rust::{
+  memory::Memory,
+  recorder::{IntentDiff, Recorder, TickRecord, replay},
+  world::{GameWorld, IntentQueue, MemoryWorld, WorldSnapshot, new_position},
+};
+use screeps::{RoomName, StructureType, Terrain};
+
+#[test]
+fn snapshots_keep_the_terrain() {
+  let room_name = RoomName::new("W1N1").unwrap();
+  let mut world = MemoryWorld::new();
+  world.set_terrain(new_position(10, 10, room_name), Terrain::Wall);
+  world.set_terrain(new_position(11, 10, room_name), Terrain::Swamp);
+
+  let snapshot: WorldSnapshot = serde_json::from_str(&serde_json::to_string(&WorldSnapshot::capture(&world)).unwrap()).unwrap();
+  let replayed = MemoryWorld::from_snapshot(&snapshot);
+  assert_eq!(replayed.terrain(new_position(10, 10, room_name)), Terrain::Wall);
+  assert_eq!(replayed.terrain(new_position(11, 10, room_name)), Terrain::Swamp);
+  assert_eq!(replayed.find_path(new_position(9, 10, room_name), new_position(12, 10, room_name), 0), world.find_path(new_position(9, 10, room_name), new_position(12, 10, room_name), 0));
+}
+
+#[test]
+fn records_keep_the_readable_storage_segments() {
+  let room_name = RoomName::new("W1N1").unwrap();
+  let mut world = MemoryWorld::new();
+  world.add_room(room_name);
+  world.set_segment(0, "shard");
+  world.set_segment(1, "not active");
+  world.active_segments.insert(0);
+
+  let recorder = Recorder { remaining_ticks: 1, ..Default::default() };
+  let record: TickRecord = recorder.capture(&world, &Memory::new(&world)).unwrap();
+  assert_eq!(record.segments, vec![(0, String::from("shard"))]);
+}
+
+#[test]
+fn recorded_ticks_replay_to_the_same_intents() {
+  let room_name = RoomName::new("W1N1").unwrap();
+  let mut world = MemoryWorld::new();
+  world.add_structure(StructureType::Spawn, new_position(25, 25, room_name), 300, 300);
+  world.add_source(new_position(25, 35, room_name), screeps::constants::SOURCE_ENERGY_CAPACITY);
+  // A swamp across the direct route, the haulers' paths have to weigh it the same way in both runs
+  for x in 22..=28 {
+    world.set_terrain(new_position(x, 30, room_name), Terrain::Swamp);
+  }
+  let mut mem = Memory::new(&world);
+  world.end_tick(); // Hive::new already took the spawn for its first tick
+  mem.recorder.remaining_ticks = 60;
+
+  // The same steps the game loop takes
+  for _ in 0..60 {
+    let record = mem.recorder.capture(&world, &mem).unwrap();
+    let mut intents = IntentQueue::new(&mut world, false, false);
+    mem.run(&mut intents);
+    let results = intents.dispatch();
+    mem.storage.activate(&mut world);
+    mem.note_dispatched(&results);
+    mem.recorder.store(&mut world, record, &results);
+
+    let (segment_id, time) = *mem.recorder.recorded.back().unwrap();
+    assert_eq!(time, world.time);
+    let record: TickRecord = serde_json::from_str(&world.segments[&segment_id]).unwrap();
+    let diff = IntentDiff::between(&record.intents, &replay(&record));
+    assert!(diff.is_empty(), "tick {} replayed differently: {:?}", time, diff);
+    world.end_tick();
+  }
+}
@@ -0,0 +1,30 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::path::PathBuf;
+
+use kaoscreeps_rust::scenario::Scenario;
+
+fn scenario_paths() -> Vec<PathBuf> {
+  let directory = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("scenarios");
+  let mut paths = std::fs::read_dir(&directory)
+    .unwrap()
+    .map(|entry| entry.unwrap().path())
+    .filter(|path| path.extension().map(|extension| extension == "json").unwrap_or(false))
+    .collect::<Vec<_>>();
+  paths.sort();
+  paths
+}
+
+#[test]
+fn scenarios_meet_expectations() {
+  let mut failures = vec![];
+
+  for path in scenario_paths() {
+    let report = Scenario::load(&path).and_then(|scenario| scenario.run()).unwrap();
+    for failure in report.failures.iter() {
+      failures.push(format!("{} ({:?}): {}", report.name, path.file_name().unwrap(), failure));
+    }
+  }
+
+  assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
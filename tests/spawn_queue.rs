@@ -0,0 +1,126 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use kaoscreeps_rust::{
+  hive::{GetCreepError, Hive, role_names, spawn_room::{SpawnPriority, SpawnRoom}},
+  world::{IntentQueue, MemoryWorld, new_position},
+};
+use screeps::{Part, RoomName, StructureType};
+
+fn world(energy: u32) -> (MemoryWorld, RoomName) {
+  let room_name = RoomName::new("W1N1").unwrap();
+  let mut world = MemoryWorld::new();
+  world.add_structure(StructureType::Spawn, new_position(25, 25, room_name), energy, 300);
+  (world, room_name)
+}
+
+#[test]
+fn requests_are_ordered_by_priority_and_deduplicated() {
+  let (world, room_name) = world(300);
+  let mut spawn_room = SpawnRoom::new(&world, room_name);
+
+  assert_eq!(spawn_room.request_spawn(&world, "hauler", vec![Part::Carry, Part::Move], SpawnPriority::Normal), 0);
+  assert_eq!(spawn_room.request_spawn(&world, "harvester", vec![Part::Work], SpawnPriority::High), 0);
+  assert_eq!(spawn_room.request_spawn(&world, "hauler", vec![Part::Carry, Part::Carry, Part::Move], SpawnPriority::Normal), 1);
+
+  let names = spawn_room.pending_requests().iter().map(|request| request.name.as_str()).collect::<Vec<_>>();
+  assert_eq!(names, vec!["harvester", "hauler"]);
+  assert_eq!(spawn_room.pending_requests()[1].body.len(), 3);
+}
+
+#[test]
+fn an_unaffordable_request_holds_back_cheaper_ones() {
+  let (mut world, room_name) = world(250);
+  let mut spawn_room = SpawnRoom::new(&world, room_name);
+  spawn_room.request_spawn(&world, "harvester", vec![Part::Work, Part::Work, Part::Work], SpawnPriority::High);
+  spawn_room.request_spawn(&world, "scout", vec![Part::Move], SpawnPriority::Low);
+
+  spawn_room.run(&mut world);
+  assert!(world.creeps.is_empty());
+  assert_eq!(spawn_room.pending_requests().len(), 2);
+
+  world.structures.values_mut().for_each(|spawn| spawn.energy = 300);
+  spawn_room.run(&mut world);
+  assert!(world.creeps.contains_key("harvester"));
+  assert_eq!(spawn_room.pending_requests()[0].dispatched, Some(world.time));
+
+  world.end_tick();
+  spawn_room.run(&mut world);
+  assert_eq!(spawn_room.pending_requests().iter().map(|request| request.name.as_str()).collect::<Vec<_>>(), vec!["scout"]);
+}
+
+#[test]
+fn stale_and_impossible_requests_are_dropped() {
+  let (mut world, room_name) = world(0);
+  let mut spawn_room = SpawnRoom::new(&world, room_name);
+  spawn_room.request_spawn(&world, "giant", vec![Part::Work; 10], SpawnPriority::High);
+  spawn_room.request_spawn(&world, "forgotten", vec![Part::Move], SpawnPriority::Normal);
+
+  spawn_room.run(&mut world);
+  assert_eq!(spawn_room.pending_requests().len(), 1);
+
+  world.time += 100;
+  spawn_room.run(&mut world);
+  assert!(spawn_room.pending_requests().is_empty());
+}
+
+#[test]
+fn requests_are_kept_until_the_creep_appears() {
+  let (mut world, room_name) = world(300);
+  let mut spawn_room = SpawnRoom::new(&world, room_name);
+  spawn_room.request_spawn(&world, "hauler", vec![Part::Carry, Part::Move], SpawnPriority::Normal);
+
+  // A dry run accepts the spawn without it ever reaching the game
+  let mut intents = IntentQueue::new(&mut world, true, false);
+  spawn_room.run(&mut intents);
+  intents.dispatch();
+  assert_eq!(spawn_room.pending_requests()[0].dispatched, Some(world.time));
+
+  world.end_tick();
+  spawn_room.run(&mut world);
+  assert!(world.creeps.contains_key("hauler"));
+  assert_eq!(spawn_room.pending_requests()[0].dispatched, Some(world.time));
+
+  world.end_tick();
+  spawn_room.run(&mut world);
+  assert!(spawn_room.pending_requests().is_empty());
+}
+
+#[test]
+fn get_creep_only_queues() {
+  let (mut world, room_name) = world(300);
+  let mut hive = Hive::new(&world, room_name);
+  world.end_tick(); // Hive::new already took the spawn for its first tick
+
+  let result = hive.get_creep(&mut world, String::from("hauler"), &room_name, SpawnPriority::Normal, |_| Ok(vec![Part::Carry, Part::Move]));
+  assert!(matches!(result, Err(GetCreepError::Queued(0))));
+  assert!(world.creeps.is_empty());
+
+  hive.spawn_rooms.get_mut(&room_name).unwrap().run(&mut world);
+  assert!(matches!(hive.get_creep(&mut world, String::from("hauler"), &room_name, SpawnPriority::Normal, |_| Ok(vec![])), Err(GetCreepError::SpawningInProgress)));
+}
+
+#[test]
+fn get_role_creeps_queues_a_successor_before_the_creep_dies() {
+  let (mut world, room_name) = world(300);
+  let mut hive = Hive::new(&world, room_name);
+  world.end_tick(); // Hive::new already took the spawn for its first tick
+  world.add_creep("hauler", new_position(25, 27, room_name), vec![Part::Carry, Part::Move]);
+  let queued = |hive: &Hive| hive.spawn_rooms[&room_name].pending_requests().iter().map(|request| request.name.to_owned()).collect::<Vec<_>>();
+
+  let holders = hive.get_role_creeps(&mut world, "hauler", &room_name, SpawnPriority::Normal, 20, |_| Ok(vec![Part::Carry, Part::Move])).unwrap();
+  assert_eq!(holders.len(), 1);
+  assert!(queued(&hive).is_empty());
+
+  world.creeps.get_mut("hauler").unwrap().ticks_to_live = Some(20);
+  let holders = hive.get_role_creeps(&mut world, "hauler", &room_name, SpawnPriority::Normal, 20, |_| Ok(vec![Part::Carry, Part::Move])).unwrap();
+  assert_eq!(holders[0].name, "hauler");
+  assert_eq!(queued(&hive), vec![role_names("hauler")[1].to_owned()]);
+
+  // Once the successor is spawned both creeps hold the role, the one that dies first listed first
+  hive.spawn_rooms.get_mut(&room_name).unwrap().run(&mut world);
+  for _ in 0..2 * screeps::constants::CREEP_SPAWN_TIME {
+    world.end_tick();
+  }
+  let holders = hive.get_role_creeps(&mut world, "hauler", &room_name, SpawnPriority::Normal, 20, |_| Ok(vec![Part::Carry, Part::Move])).unwrap();
+  assert_eq!(holders.iter().map(|creep| creep.name.as_str()).collect::<Vec<_>>(), vec!["hauler", "hauler+"]);
+}
@@ -0,0 +1,106 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::collections::HashMap;
+
+use kaoscreeps_rust::{
+  constants::{MAX_ACTIVE_SEGMENTS, SEGMENT_SIZE_LIMIT, STORAGE_SEGMENT_COUNT},
+  memory::storage::{SEGMENT_REGISTRY, SegmentOwner, SegmentStorage, ShardLoad, owner_of, segments_of},
+  world::{GameWorld, MemoryWorld},
+};
+
+fn intel(rooms: usize, padding: usize) -> HashMap<String,String> {
+  (0..rooms).map(|room| (format!("W{}N1", room), "x".repeat(padding))).collect()
+}
+
+// Loads a shard, giving its segments the tick they need to become active
+fn load_next_tick(storage: &mut SegmentStorage, world: &mut MemoryWorld, name: &str) -> ShardLoad<HashMap<String,String>> {
+  match storage.load(world, name) {
+    ShardLoad::Pending => {
+      storage.activate(world);
+      world.end_tick();
+      storage.load(world, name)
+    },
+    other => other,
+  }
+}
+
+#[test]
+fn registered_ranges_do_not_overlap() {
+  for segment_id in 0..100u8 {
+    let owners = SEGMENT_REGISTRY.iter().filter(|(_, start, count)| (*start..(start + count)).contains(&segment_id)).count();
+    assert!(owners <= 1, "segment {} has {} owners", segment_id, owners);
+  }
+  assert!(SEGMENT_REGISTRY.iter().all(|(_, start, count)| *start as u32 + *count as u32 <= 100));
+  assert_eq!(owner_of(segments_of(SegmentOwner::Recorder).start), Some(SegmentOwner::Recorder));
+}
+
+#[test]
+fn shards_load_a_tick_after_they_are_requested() {
+  let mut world = MemoryWorld::new();
+  let mut storage = SegmentStorage::default();
+  storage.store(&mut world, "intel", &intel(3, 10)).unwrap();
+
+  assert_eq!(storage.load::<HashMap<String,String>>(&world, "intel"), ShardLoad::Pending);
+  storage.activate(&mut world);
+  world.end_tick();
+  assert_eq!(storage.load(&world, "intel"), ShardLoad::Loaded(intel(3, 10)));
+}
+
+#[test]
+fn large_shards_span_several_segments() {
+  let mut world = MemoryWorld::new();
+  let mut storage = SegmentStorage::default();
+  let value = intel(4, SEGMENT_SIZE_LIMIT / 2);
+  storage.store(&mut world, "intel", &value).unwrap();
+
+  assert!(storage.shards["intel"].segments.len() > 1);
+  assert_eq!(load_next_tick(&mut storage, &mut world, "intel"), ShardLoad::Loaded(value));
+}
+
+#[test]
+fn rewriting_a_shard_leaves_the_old_copy_alone() {
+  let mut world = MemoryWorld::new();
+  let mut storage = SegmentStorage::default();
+  storage.store(&mut world, "intel", &intel(1, 10)).unwrap();
+  let old_segments = storage.shards["intel"].segments.to_owned();
+
+  storage.store(&mut world, "intel", &intel(2, 10)).unwrap();
+  assert!(storage.shards["intel"].segments.iter().all(|segment_id| !old_segments.contains(segment_id)));
+  assert!(storage.take_changed());
+  assert_eq!(load_next_tick(&mut storage, &mut world, "intel"), ShardLoad::Loaded(intel(2, 10)));
+}
+
+#[test]
+fn storage_never_writes_outside_its_range() {
+  let mut world = MemoryWorld::new();
+  let mut storage = SegmentStorage::default();
+  for shard in 0..STORAGE_SEGMENT_COUNT {
+    storage.store(&mut world, &format!("shard{}", shard), &intel(1, 10)).unwrap();
+    world.end_tick();
+  }
+
+  assert!(storage.store(&mut world, "one_too_many", &intel(1, 10)).is_err());
+  assert!(world.segments.keys().all(|segment_id| owner_of(*segment_id) == Some(SegmentOwner::Storage)));
+}
+
+#[test]
+fn shards_wait_for_segment_writes_left_this_tick() {
+  let mut world = MemoryWorld::new();
+  let mut storage = SegmentStorage::default();
+  for segment_id in segments_of(SegmentOwner::Recorder).take(MAX_ACTIVE_SEGMENTS) {
+    world.set_segment(segment_id, "taken");
+  }
+
+  assert!(storage.store(&mut world, "intel", &intel(1, 10)).is_err());
+  assert!(storage.shards.is_empty());
+  world.end_tick();
+  assert!(storage.store(&mut world, "intel", &intel(1, 10)).is_ok());
+}
+
+#[test]
+fn missing_shards_are_reported() {
+  let world = MemoryWorld::new();
+  let mut storage = SegmentStorage::default();
+  assert_eq!(storage.load::<HashMap<String,String>>(&world, "intel"), ShardLoad::Missing);
+  assert_eq!(world.segment(0), None);
+}
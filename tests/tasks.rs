@@ -0,0 +1,508 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use kaoscreeps_rust::{
+  hive::{group_tasks::{GroupAction, GroupTask}, tasks::{Condition, Deadline, Failure, ScheduledTask, Task, TaskEvent, TaskOwner, TaskPriority, TaskReturn, TaskStatus, TaskTarget, Tasks}},
+  world::{IntentQueue, IntentSlot, MemoryWorld, new_position},
+};
+use screeps::{Part, RawObjectId, ResourceType, ReturnCode, RoomName, StructureType};
+
+fn world() -> (MemoryWorld, RawObjectId) {
+  let room_name = RoomName::new("W1N1").unwrap();
+  let mut world = MemoryWorld::new();
+  let spawn_id = world.add_structure(StructureType::Spawn, new_position(25, 25, room_name), 300, 300);
+  world.add_creep("worker", new_position(25, 26, room_name), vec![Part::Work, Part::Carry, Part::Move]);
+  (world, spawn_id)
+}
+
+#[test]
+fn every_action_task_survives_serialization() {
+  let id: RawObjectId = "5bbcaa9099171b1f4c0f1a01".parse().unwrap();
+  let tasks = vec![
+    Task::Transfer { target: id, resource_type: ResourceType::Energy, amount: None },
+    Task::Withdraw { target: id, resource_type: ResourceType::Energy, amount: Some(10) },
+    Task::Pickup(id),
+    Task::Drop { resource_type: ResourceType::Energy, amount: None },
+    Task::Build(id),
+    Task::Repair(id),
+    Task::UpgradeController(id),
+    Task::Dismantle(id),
+    Task::ClaimController(id),
+    Task::ReserveController(id),
+    Task::Heal(id),
+    Task::Attack(id),
+    Task::RangedAttack(id),
+  ];
+  for task in tasks {
+    let serialized = serde_json::to_string(&task).unwrap();
+    let deserialized: Task = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(serde_json::to_string(&deserialized).unwrap(), serialized);
+  }
+}
+
+#[test]
+fn withdraw_then_drop_moves_energy() {
+  let (mut world, spawn_id) = world();
+
+  let mut withdraw = Task::Withdraw { target: spawn_id, resource_type: ResourceType::Energy, amount: None };
+  assert!(matches!(withdraw.run(&mut world, "worker"), TaskReturn::Complete));
+  world.end_tick();
+  assert_eq!(world.creeps["worker"].energy, 50);
+  assert_eq!(world.structures[&spawn_id].energy, 250);
+
+  let mut drop = Task::Drop { resource_type: ResourceType::Energy, amount: Some(20) };
+  assert!(matches!(drop.run(&mut world, "worker"), TaskReturn::Complete));
+  world.end_tick();
+  assert_eq!(world.creeps["worker"].energy, 30);
+  assert_eq!(world.resources.values().map(|resource| resource.amount).sum::<u32>(), 20);
+}
+
+#[test]
+fn unresolvable_targets_fail_instead_of_panicking() {
+  let (mut world, _) = world();
+  let missing: RawObjectId = "5bbcaa9099171b1f4c0f1aff".parse().unwrap();
+
+  for mut task in [Task::Repair(missing), Task::Heal(missing), Task::Attack(missing), Task::Build(missing), Task::UpgradeController(missing)] {
+    assert!(matches!(task.run(&mut world, "worker"), TaskReturn::Err(error) if error.return_code() == ReturnCode::InvalidTarget));
+  }
+}
+
+#[test]
+fn actions_check_body_parts() {
+  let (mut world, spawn_id) = world();
+  assert!(matches!(Task::Attack(spawn_id).run(&mut world, "worker"), TaskReturn::Err(error) if error.return_code() == ReturnCode::NoBodypart));
+  assert!(matches!(Task::Dismantle(spawn_id).run(&mut world, "worker"), TaskReturn::Complete));
+}
+
+fn run_until_done(world: &mut MemoryWorld, creep_name: &str, task: &mut Task, max_ticks: u32) -> u32 {
+  for tick in 0..max_ticks {
+    match task.run(world, creep_name) {
+      TaskReturn::ProgressMade => world.end_tick(),
+      TaskReturn::Complete => return tick,
+      TaskReturn::Err(return_code) => panic!("task failed with {:?}", return_code),
+    }
+  }
+  panic!("task did not finish in {} ticks", max_ticks);
+}
+
+#[test]
+fn move_to_walks_around_walls_and_stops_in_range() {
+  let room_name = RoomName::new("W1N1").unwrap();
+  let mut world = MemoryWorld::new();
+  world.add_creep("walker", new_position(10, 10, room_name), vec![Part::Move]);
+  for y in 5..15 {
+    world.set_terrain(new_position(12, y, room_name), screeps::Terrain::Wall);
+  }
+
+  let target = new_position(14, 10, room_name);
+  let mut task = Task::MoveTo { target, range: 1, path: Default::default() };
+  run_until_done(&mut world, "walker", &mut task, 30);
+  assert!(world.creeps["walker"].pos.get_range_to(&target) <= 1);
+}
+
+#[test]
+fn move_to_keeps_its_path_through_memory() {
+  let room_name = RoomName::new("W1N1").unwrap();
+  let mut world = MemoryWorld::new();
+  world.add_creep("walker", new_position(10, 10, room_name), vec![Part::Move]);
+
+  let target = new_position(20, 10, room_name);
+  let mut task = Task::MoveTo { target, range: 0, path: Default::default() };
+  assert!(matches!(task.run(&mut world, "walker"), TaskReturn::ProgressMade));
+  world.end_tick();
+
+  let mut task: Task = serde_json::from_str(&serde_json::to_string(&task).unwrap()).unwrap();
+  match &task {
+    Task::MoveTo { path, .. } => assert_eq!(path.directions.len(), 10),
+    other => panic!("expected MoveTo, got {:?}", other),
+  }
+  run_until_done(&mut world, "walker", &mut task, 20);
+  assert_eq!(world.creeps["walker"].pos, target);
+}
+
+#[test]
+fn blocked_moves_are_not_progress_and_find_a_way_around() {
+  let room_name = RoomName::new("W1N1").unwrap();
+  let mut world = MemoryWorld::new();
+  world.add_creep("walker", new_position(10, 10, room_name), vec![Part::Move]);
+  let target = new_position(20, 10, room_name);
+  let mut task = Task::MoveTo { target, range: 0, path: Default::default() };
+  assert!(matches!(task.run(&mut world, "walker"), TaskReturn::ProgressMade));
+  world.end_tick();
+
+  // Built across the stored path after it was found
+  world.add_structure(StructureType::Spawn, new_position(12, 10, room_name), 0, 300);
+  assert!(matches!(task.run(&mut world, "walker"), TaskReturn::ProgressMade));
+  world.end_tick();
+  assert!(matches!(task.run(&mut world, "walker"), TaskReturn::Err(error) if error.return_code() == ReturnCode::NoPath));
+
+  for _ in 0..20 {
+    world.end_tick();
+    if let TaskReturn::Complete = task.run(&mut world, "walker") {
+      break;
+    }
+  }
+  assert_eq!(world.creeps["walker"].pos, target);
+}
+
+#[test]
+fn move_to_reports_missing_paths() {
+  let mut world = MemoryWorld::new();
+  world.add_creep("walker", new_position(10, 10, RoomName::new("W1N1").unwrap()), vec![Part::Move]);
+
+  let mut task = Task::MoveTo { target: new_position(10, 10, RoomName::new("W2N1").unwrap()), range: 1, path: Default::default() };
+  assert!(matches!(task.run(&mut world, "walker"), TaskReturn::Err(error) if error.return_code() == ReturnCode::NoPath));
+}
+
+#[test]
+fn transient_failures_are_retried_then_abandoned() {
+  let (mut world, spawn_id) = world();
+  // Nothing to transfer yet, NotEnough is recoverable
+  let mut scheduled = ScheduledTask::new(Task::Transfer { target: spawn_id, resource_type: ResourceType::Energy, amount: None }).with_retries(2);
+  assert_eq!(Failure::classify(ReturnCode::NotEnough), Failure::Recoverable);
+
+  assert_eq!(scheduled.run(&mut world, "worker"), TaskStatus::Running);
+  assert_eq!(scheduled.run(&mut world, "worker"), TaskStatus::Running);
+  assert!(matches!(scheduled.run(&mut world, "worker"), TaskStatus::Failed(error) if error.return_code() == ReturnCode::NotEnough));
+}
+
+#[test]
+fn fatal_failures_are_abandoned_immediately() {
+  let (mut world, _) = world();
+  let missing: RawObjectId = "5bbcaa9099171b1f4c0f1aff".parse().unwrap();
+  let mut scheduled = ScheduledTask::new(Task::Repair(missing));
+  assert!(matches!(scheduled.run(&mut world, "worker"), TaskStatus::Failed(error) if error.return_code() == ReturnCode::InvalidTarget));
+}
+
+#[test]
+fn deadlines_end_tasks_that_never_finish() {
+  let (mut world, spawn_id) = world();
+  let mut tasks = Tasks::new();
+  let perpetual = Task::Perpetual(Box::new(Task::Withdraw { target: spawn_id, resource_type: ResourceType::Energy, amount: Some(1) }));
+  tasks.add_scheduled_task(String::from("worker"), ScheduledTask::new(perpetual).with_deadline(Deadline::Ticks(5))).unwrap();
+
+  for _ in 0..5 {
+    tasks.run(&mut world);
+    assert!(tasks.has_task("worker"));
+    world.end_tick();
+  }
+  tasks.run(&mut world);
+  assert!(!tasks.has_task("worker"));
+}
+
+#[test]
+fn long_deadlines_do_not_overflow() {
+  assert!(!Deadline::Ticks(u32::MAX).passed(u32::MAX - 1, 10));
+  assert!(Deadline::Ticks(u32::MAX).passed(u32::MAX, 10));
+}
+
+#[test]
+fn tasks_for_missing_creeps_report_their_death() {
+  let (mut world, _) = world();
+  let mut scheduled = ScheduledTask::new(Task::Move(screeps::Direction::Top)).with_deadline(Deadline::Tick(world.time + 10));
+  assert_eq!(scheduled.run(&mut world, "nobody"), TaskStatus::CreepDied);
+}
+
+#[test]
+fn harvest_then_haul_cycle_as_data() {
+  let room_name = RoomName::new("W1N1").unwrap();
+  let mut world = MemoryWorld::new();
+  let spawn_id = world.add_structure(StructureType::Spawn, new_position(25, 25, room_name), 0, 300);
+  let source_id = world.add_source(new_position(27, 25, room_name), 3000);
+  world.add_creep("worker", new_position(26, 25, room_name), vec![Part::Work, Part::Carry, Part::Move]);
+
+  let cycle = Task::MultiStep(vec![
+    Box::new(Task::Until { condition: Condition::StoreFull, task: Box::new(Task::Harvest(source_id)), progress: None }),
+    Box::new(Task::Until { condition: Condition::StoreEmpty, task: Box::new(Task::Transfer { target: spawn_id, resource_type: ResourceType::Energy, amount: None }), progress: None }),
+  ].into());
+  let mut task = Task::Repeat { times: 2, task: Box::new(cycle), completed: 0, progress: None };
+
+  // Round trip through JSON every tick, the way it lives in Memory
+  for _ in 0..100 {
+    task = serde_json::from_str(&serde_json::to_string(&task).unwrap()).unwrap();
+    match task.run(&mut world, "worker") {
+      TaskReturn::ProgressMade => world.end_tick(),
+      TaskReturn::Complete => break,
+      TaskReturn::Err(return_code) => panic!("cycle failed with {:?}", return_code),
+    }
+  }
+  assert!(matches!(task, Task::Repeat { completed: 2, .. }));
+  assert_eq!(world.stats.energy_delivered, 100);
+}
+
+#[test]
+fn conditions_combine() {
+  let (world, spawn_id) = world();
+  let missing: RawObjectId = "5bbcaa9099171b1f4c0f1aff".parse().unwrap();
+
+  assert!(Condition::StoreEmpty.evaluate(&world, "worker"));
+  assert!(Condition::All(vec![Condition::TargetExists(spawn_id), Condition::Not(Box::new(Condition::TargetExists(missing)))]).evaluate(&world, "worker"));
+  assert!(Condition::Any(vec![Condition::StoreFull, Condition::RoomEnergyAbove(200)]).evaluate(&world, "worker"));
+  assert!(!Condition::TicksToLiveBelow(100).evaluate(&world, "worker"));
+  assert!(!Condition::StoreEmpty.evaluate(&world, "nobody"));
+}
+
+#[test]
+fn if_else_sticks_with_its_first_choice() {
+  let (mut world, spawn_id) = world();
+  let mut task = Task::IfElse {
+    condition: Condition::StoreEmpty,
+    then: Box::new(Task::Withdraw { target: spawn_id, resource_type: ResourceType::Energy, amount: Some(10) }),
+    otherwise: Box::new(Task::Drop { resource_type: ResourceType::Energy, amount: None }),
+    chosen: None,
+  };
+  assert!(matches!(task.run(&mut world, "worker"), TaskReturn::Complete));
+  world.end_tick();
+  assert!(matches!(task, Task::IfElse { chosen: Some(true), .. }));
+  assert_eq!(world.creeps["worker"].energy, 10);
+}
+
+#[test]
+fn intent_slots_follow_action_priorities() {
+  assert!(IntentSlot::Move.compatible_with(IntentSlot::Harvest));
+  assert!(IntentSlot::Transfer.compatible_with(IntentSlot::Withdraw));
+  assert!(IntentSlot::RangedAttack.compatible_with(IntentSlot::Heal));
+  assert!(!IntentSlot::Harvest.compatible_with(IntentSlot::Build));
+  assert!(!IntentSlot::Repair.compatible_with(IntentSlot::RangedAttack));
+  assert!(!IntentSlot::Move.compatible_with(IntentSlot::Move));
+}
+
+#[test]
+fn simultaneous_harvests_on_the_tick_it_arrives() {
+  let room_name = RoomName::new("W1N1").unwrap();
+  let source_pos = new_position(30, 25, room_name);
+  let harvest_when_there = |source_id| Task::Until { condition: Condition::StoreFull, task: Box::new(Task::Harvest(source_id)), progress: None };
+
+  let mut sequential_world = MemoryWorld::new();
+  let source_id = sequential_world.add_source(source_pos, 3000);
+  sequential_world.add_creep("worker", new_position(25, 25, room_name), vec![Part::Work, Part::Carry, Part::Move]);
+  let mut sequential = Task::MultiStep(vec![
+    Box::new(Task::MoveTo { target: source_pos, range: 1, path: Default::default() }),
+    Box::new(harvest_when_there(source_id)),
+  ].into());
+  let sequential_ticks = run_until_done(&mut sequential_world, "worker", &mut sequential, 50);
+
+  let mut simultaneous_world = MemoryWorld::new();
+  let source_id = simultaneous_world.add_source(source_pos, 3000);
+  simultaneous_world.add_creep("worker", new_position(25, 25, room_name), vec![Part::Work, Part::Carry, Part::Move]);
+  let mut simultaneous = Task::Simultaneous(vec![
+    Box::new(Task::MoveTo { target: source_pos, range: 1, path: Default::default() }),
+    Box::new(harvest_when_there(source_id)),
+  ]);
+  let simultaneous_ticks = run_until_done(&mut simultaneous_world, "worker", &mut simultaneous, 50);
+
+  assert!(simultaneous_ticks < sequential_ticks);
+  assert_eq!(simultaneous_world.creeps["worker"].free_capacity(), 0);
+}
+
+#[test]
+fn simultaneous_holds_back_conflicting_intents() {
+  let (mut world, spawn_id) = world();
+  let source_id = world.add_source(new_position(24, 27, RoomName::new("W1N1").unwrap()), 3000);
+  let mut task = Task::Simultaneous(vec![
+    Box::new(Task::Harvest(source_id)),
+    Box::new(Task::Dismantle(spawn_id)),
+    Box::new(Task::Move(screeps::Direction::Bottom)),
+  ]);
+
+  let mut queue = IntentQueue::new(&mut world, false, false);
+  assert!(matches!(task.run(&mut queue, "worker"), TaskReturn::ProgressMade));
+  let slots = queue.intents().iter().map(|intent| intent.slot()).collect::<Vec<_>>();
+  assert_eq!(slots, vec![IntentSlot::Harvest, IntentSlot::Move]);
+
+  // Only the dismantle is left for the next tick
+  assert!(matches!(&task, Task::Simultaneous(task_list) if task_list.len() == 1));
+  assert_eq!(task.slots(), vec![IntentSlot::Dismantle]);
+}
+
+fn tow_world() -> (MemoryWorld, screeps::Position) {
+  let room_name = RoomName::new("W1N1").unwrap();
+  let mut world = MemoryWorld::new();
+  let source_pos = new_position(20, 10, room_name);
+  world.add_source(source_pos, 3000);
+  world.add_creep("hauler", new_position(10, 10, room_name), vec![Part::Move, Part::Carry, Part::Move, Part::Carry]);
+  world.add_creep("harvester", new_position(9, 10, room_name), vec![Part::Work, Part::Work]);
+  (world, source_pos)
+}
+
+#[test]
+fn group_tow_delivers_the_follower_and_releases_everyone() {
+  let (mut world, source_pos) = tow_world();
+  let mut tasks = Tasks::new();
+  let tow = GroupAction::Tow { target: source_pos, range: 1, path: Default::default() };
+  tasks.add_group(String::from("hauler"), GroupTask::new(vec![String::from("harvester")], tow)).unwrap();
+  assert!(tasks.has_task("hauler") && tasks.has_task("harvester"));
+  assert_eq!(tasks.group_of("harvester"), Some("hauler"));
+
+  for _ in 0..60 {
+    if tasks.groups.is_empty() {
+      break;
+    }
+    tasks.run(&mut world);
+    world.end_tick();
+  }
+  assert!(tasks.groups.is_empty());
+  assert!(world.creeps["harvester"].pos.get_range_to(&source_pos) <= 1);
+  assert!(!tasks.has_task("hauler") && !tasks.has_task("harvester"));
+}
+
+#[test]
+fn group_members_cannot_be_given_a_second_task() {
+  let (_, source_pos) = tow_world();
+  let mut tasks = Tasks::new();
+  tasks.add_task(String::from("harvester"), Task::Move(screeps::Direction::Top)).unwrap();
+  let tow = GroupAction::Tow { target: source_pos, range: 1, path: Default::default() };
+  assert!(tasks.add_group(String::from("hauler"), GroupTask::new(vec![String::from("harvester")], tow)).is_err());
+  assert!(tasks.groups.is_empty());
+}
+
+#[test]
+fn group_members_cannot_be_given_a_solo_task() {
+  let (_, source_pos) = tow_world();
+  let mut tasks = Tasks::new();
+  let tow = GroupAction::Tow { target: source_pos, range: 1, path: Default::default() };
+  tasks.add_group(String::from("hauler"), GroupTask::new(vec![String::from("harvester")], tow)).unwrap();
+  assert!(tasks.add_task(String::from("harvester"), Task::Move(screeps::Direction::Top)).is_err());
+  assert!(tasks.add_scheduled_task(String::from("hauler"), ScheduledTask::new(Task::Move(screeps::Direction::Top))).is_err());
+  assert!(tasks.task_list.is_empty());
+}
+
+#[test]
+fn losing_a_member_releases_the_group() {
+  let (mut world, source_pos) = tow_world();
+  let mut tasks = Tasks::new();
+  let tow = GroupAction::Tow { target: source_pos, range: 1, path: Default::default() };
+  tasks.add_group(String::from("hauler"), GroupTask::new(vec![String::from("harvester")], tow)).unwrap();
+  tasks.run(&mut world);
+  world.end_tick();
+
+  world.creeps.remove("harvester");
+  tasks.run(&mut world);
+  assert!(!tasks.has_task("hauler"));
+}
+
+#[test]
+fn owned_tasks_report_how_they_ended() {
+  let (mut world, spawn_id) = world();
+  let room_name = RoomName::new("W1N1").unwrap();
+  let mut tasks = Tasks::new();
+  let withdraw = Task::Withdraw { target: spawn_id, resource_type: ResourceType::Energy, amount: Some(10) };
+  tasks.add_scheduled_task(String::from("worker"), ScheduledTask::new(withdraw).with_owner(TaskOwner::SpawnRoom(room_name))).unwrap();
+  tasks.add_task(String::from("nobody"), Task::Move(screeps::Direction::Top)).unwrap();
+
+  tasks.run(&mut world);
+  assert_eq!(tasks.take_events(), vec![TaskEvent {
+    owner: TaskOwner::SpawnRoom(room_name),
+    creep_name: String::from("worker"),
+    status: TaskStatus::Completed,
+    time: world.time,
+  }]);
+  assert!(tasks.task_list.is_empty());
+  assert!(tasks.events.is_empty());
+}
+
+#[test]
+fn groups_report_a_dead_member_to_their_owner() {
+  let (mut world, source_pos) = tow_world();
+  let room_name = RoomName::new("W1N1").unwrap();
+  let mut tasks = Tasks::new();
+  let tow = GroupAction::Tow { target: source_pos, range: 1, path: Default::default() };
+  tasks.add_group(String::from("hauler"), GroupTask::new(vec![String::from("harvester")], tow).with_owner(TaskOwner::MineRoom(room_name))).unwrap();
+
+  world.creeps.remove("harvester");
+  tasks.run(&mut world);
+  let events = tasks.take_events();
+  assert_eq!(events.len(), 1);
+  assert_eq!(events[0].owner, TaskOwner::MineRoom(room_name));
+  assert_eq!(events[0].creep_name, "hauler");
+  assert_eq!(events[0].status, TaskStatus::CreepDied);
+}
+
+#[test]
+fn urgent_tasks_preempt_and_the_interrupted_task_resumes() {
+  let (mut world, spawn_id) = world();
+  let mut tasks = Tasks::new();
+  let withdraw = Task::Withdraw { target: spawn_id, resource_type: ResourceType::Energy, amount: Some(10) };
+  tasks.add_task(String::from("worker"), Task::Repeat { times: 3, task: Box::new(withdraw), completed: 0, progress: None }).unwrap();
+  tasks.run(&mut world);
+  world.end_tick();
+
+  let drop = Task::Drop { resource_type: ResourceType::Energy, amount: Some(5) };
+  tasks.add_scheduled_task(String::from("worker"), ScheduledTask::new(drop).with_priority(TaskPriority::Urgent)).unwrap();
+  assert!(matches!(tasks.current_task("worker").unwrap().task, Task::Drop { .. }));
+  tasks.run(&mut world);
+  world.end_tick();
+  assert_eq!(world.creeps["worker"].energy, 5);
+
+  // Picks up from its second withdrawal
+  assert!(matches!(tasks.current_task("worker").unwrap().task, Task::Repeat { completed: 1, .. }));
+  for _ in 0..2 {
+    tasks.run(&mut world);
+    world.end_tick();
+  }
+  assert_eq!(world.creeps["worker"].energy, 25);
+  assert!(!tasks.has_task("worker"));
+}
+
+#[test]
+fn tasks_of_equal_priority_wait_their_turn() {
+  let mut tasks = Tasks::new();
+  tasks.add_task(String::from("worker"), Task::Move(screeps::Direction::Top)).unwrap();
+  tasks.add_task(String::from("worker"), Task::Move(screeps::Direction::Bottom)).unwrap();
+  tasks.add_scheduled_task(String::from("worker"), ScheduledTask::new(Task::Move(screeps::Direction::Left)).with_priority(TaskPriority::Low)).unwrap();
+
+  let directions = tasks.task_list["worker"].iter().rev().map(|scheduled_task| match scheduled_task.task {
+    Task::Move(direction) => direction,
+    _ => unreachable!(),
+  }).collect::<Vec<_>>();
+  assert_eq!(directions, vec![screeps::Direction::Top, screeps::Direction::Bottom, screeps::Direction::Left]);
+}
+
+#[test]
+fn every_waiting_task_hears_its_creep_died() {
+  let (mut world, _) = world();
+  let room_name = RoomName::new("W1N1").unwrap();
+  let mut tasks = Tasks::new();
+  tasks.add_scheduled_task(String::from("worker"), ScheduledTask::new(Task::Move(screeps::Direction::Top)).with_owner(TaskOwner::MineRoom(room_name))).unwrap();
+  tasks.add_scheduled_task(String::from("worker"), ScheduledTask::new(Task::Move(screeps::Direction::Bottom)).with_owner(TaskOwner::SpawnRoom(room_name)).with_priority(TaskPriority::High)).unwrap();
+
+  world.creeps.remove("worker");
+  tasks.run(&mut world);
+  let events = tasks.take_events();
+  assert_eq!(events.iter().map(|event| event.owner.to_owned()).collect::<Vec<_>>(), vec![TaskOwner::SpawnRoom(room_name), TaskOwner::MineRoom(room_name)]);
+  assert!(events.iter().all(|event| event.status == TaskStatus::CreepDied));
+  assert!(!tasks.has_task("worker"));
+}
+
+#[test]
+fn errors_name_the_step_that_failed() {
+  let (mut world, spawn_id) = world();
+  let missing: RawObjectId = "5bbcaa9099171b1f4c0f1aff".parse().unwrap();
+  let mut task = Task::MultiStep(vec![
+    Box::new(Task::Withdraw { target: spawn_id, resource_type: ResourceType::Energy, amount: Some(10) }),
+    Box::new(Task::Continuous(Box::new(Task::Repair(missing)))),
+  ].into());
+  assert!(matches!(task.run(&mut world, "worker"), TaskReturn::ProgressMade));
+  world.end_tick();
+
+  let error = match task.run(&mut world, "worker") {
+    TaskReturn::Err(error) => error,
+    _ => panic!("repairing a missing structure should fail"),
+  };
+  assert_eq!(error.path, vec!["MultiStep", "Continuous", "Repair"]);
+  assert_eq!(error.target, Some(TaskTarget::Object(missing)));
+  assert_eq!(error.return_codes, vec![ReturnCode::InvalidTarget]);
+  assert_eq!(error.time, world.time);
+}
+
+#[test]
+fn towing_reports_both_return_codes_instead_of_panicking() {
+  let (mut world, _) = world();
+  world.add_creep("towed", new_position(10, 10, RoomName::new("W1N1").unwrap()), vec![Part::Work]);
+  let mut task = Task::Tow(String::from("towed"), screeps::Direction::Top);
+  let error = match task.run(&mut world, "worker") {
+    TaskReturn::Err(error) => error,
+    _ => panic!("towing a creep out of reach should fail"),
+  };
+  assert_eq!(error.return_codes, vec![ReturnCode::NotInRange, ReturnCode::NotInRange]);
+  assert_eq!(error.target, Some(TaskTarget::Creep(String::from("towed"))));
+}